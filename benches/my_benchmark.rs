@@ -27,13 +27,16 @@ pub fn criterion_benchmark(c: &mut Criterion) {
         let nums: Vec<_> = encoding::parse(&input);
         c.bench_function("9 parse", |b| b.iter(|| encoding::parse(&input)));
         c.bench_function("9a", |b| b.iter(|| encoding::part1(&nums, 25)));
-        c.bench_function("9b", |b| b.iter(|| encoding::part2(&nums, 25)));
+        let target = encoding::part1(&nums, 25).unwrap();
+        c.bench_function("9b", |b| b.iter(|| encoding::part2(&nums, target)));
     }
     {
         let input = std::fs::read_to_string("inputs/day10").unwrap();
-        c.bench_function("10 parse", |b| b.iter(|| adapter::parse(&input)));
+        c.bench_function("10 parse", |b| {
+            b.iter(|| adapter::parse(&input, adapter::DEVICE_OFFSET))
+        });
 
-        let nums: Vec<_> = adapter::parse(&input);
+        let nums: Vec<_> = adapter::parse(&input, adapter::DEVICE_OFFSET);
         c.bench_function("10a", |b| b.iter(|| adapter::part1(&nums)));
         c.bench_function("10b", |b| b.iter(|| adapter::part2(&nums)));
     }
@@ -77,6 +80,24 @@ pub fn criterion_benchmark(c: &mut Criterion) {
         let input = std::fs::read_to_string("inputs/day17").unwrap();
         c.bench_function("17a", |b| b.iter(|| d17_conway::part1(&input)));
         c.bench_function("17b", |b| b.iter(|| d17_conway::part2(&input)));
+        use d17_conway::CubeBackend;
+        for (name, backend) in &[
+            ("sparse", CubeBackend::Sparse),
+            ("symmetric", CubeBackend::Symmetric),
+            ("dense", CubeBackend::Dense),
+        ] {
+            c.bench_function(&format!("17a {}", name), |b| {
+                b.iter(|| d17_conway::simulate_n::<3>(&input, 6, *backend))
+            });
+            c.bench_function(&format!("17b {}", name), |b| {
+                b.iter(|| d17_conway::simulate_n::<4>(&input, 6, *backend))
+            });
+        }
+        c.bench_function("17 6d", |b| {
+            b.iter(|| {
+                d17_conway::simulate(&input, 6, 6, CubeBackend::Symmetric)
+            })
+        });
     }
     {
         let input = std::fs::read_to_string("inputs/day18").unwrap();
@@ -1,89 +1,324 @@
-use std::collections::HashMap;
+use num::{BigUint, One, Zero};
+use rand::Rng;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+
+/// Difference between the highest adapter and the device.
+pub const DEVICE_OFFSET: u64 = 3;
+
+/// Joltage differences allowed between consecutive adapters.
+pub const DIFFS: [u64; 3] = [1, 2, 3];
 
 // Output is *sorted*
-pub fn parse(input: &str) -> Vec<u64> {
+pub fn parse(input: &str, device_offset: u64) -> Vec<u64> {
     let mut res: Vec<_> =
         input.lines().map(|x| x.parse::<u64>().unwrap()).collect();
     // Starting voltage is 0.
     res.push(0);
     res.sort_unstable();
-    // Final voltage is 3 greater than last adapter
-    res.push(res.last().unwrap() + 3);
+    // Final voltage is `device_offset` greater than last adapter
+    res.push(res.last().unwrap() + device_offset);
     res
 }
 
-// Assumes `nums` is sorted
-pub fn part1(nums: &[u64]) -> u64 {
-    let (mut ones, mut threes) = (0, 0);
-    for diff in nums.windows(2).map(|x| x[1] - x[0]) {
-        match diff {
-            1 => ones += 1,
-            3 => threes += 1,
-            _ => (),
-        }
-    }
-    ones * threes
-}
-
-pub fn part2(nums: &[u64]) -> u64 {
-    part2_helper(&mut HashMap::new(), nums[0], 1, nums)
-}
-
-// Recursive helper. Memoized via memo_table to make not insanely expensive.  Returns the number of
-// ways to connect the adapter `prev_val` to the adapters in `&nums[start_idx..]`. We pass
-// `start_idx` explicitly instead of the subslice itself, so that we can use `(prev_val,
-// start_idx)` as a cheap key into the memo table.
-fn part2_helper(
-    memo_table: &mut HashMap<(u64, usize), u64>,
-    prev_val: u64,
-    start_idx: usize,
-    nums: &[u64],
-) -> u64 {
-    if let Some(res) = memo_table.get(&(prev_val, start_idx)) {
-        // We've already computed this. Return the previous result.
-        return *res;
-    }
-
-    // Alias for the slice of interest, for convenience.
-    let slice = &nums[start_idx..nums.len()];
-
-    // We should never be called with a zero-length slice (assuming sane original input).
-    debug_assert_ne!(slice.len(), 0);
-
-    // If the gap between the previous value and the first value in the slice is too large, then
-    // there are no ways to arrange the slice.
-    if slice[0] - prev_val > 3 {
-        return 0;
-    }
-    // If there's only one value in the slice, then there's only one possibility.
-    if slice.len() == 1 {
-        return 1;
-    }
-    // Number of ways to arrange the slice if we include the first value of the slice.
-    let ways_with_next =
-        part2_helper(memo_table, slice[0], start_idx + 1, nums);
-    // Number of ways to arrange the slice if we *don't* include the first value of the slice.
-    let ways_without_next = if slice[1] - prev_val > 3 {
-        0
-    } else {
-        part2_helper(memo_table, prev_val, start_idx + 1, nums)
-    };
-    let res = ways_with_next + ways_without_next;
-    // Memoize recursive results, so that we don't need to recompute them later.
-    memo_table.insert((prev_val, start_idx), res);
-    res
+/// Product of the numbers of 1- and 3-jolt gaps. Fails if the chain is
+/// broken.
+pub fn part1(nums: &[u64]) -> Result<u64, Box<dyn Error>> {
+    let gaps = gaps(nums, &DIFFS);
+    if let Some(gap) = gaps.broken.first() {
+        return Err(format!("Chain broken: {}", gap).into());
+    }
+    let count = |diff| *gaps.histogram.get(&diff).unwrap_or(&0) as u64;
+    Ok(count(1) * count(3))
+}
+
+pub fn part2(nums: &[u64]) -> BigUint {
+    Chain::new(nums, &DIFFS).count()
+}
+
+/// The ways of connecting the first joltage to the last through a subset of
+/// the ones in between, with each step's difference in an allowed set.
+pub struct Chain {
+    // Sorted.
+    joltages: Vec<u64>,
+    diffs: Vec<u64>,
+    // The largest of `diffs`.
+    max_diff: u64,
+    // ways[i] is the number of ways to get from joltages[i] to the last one.
+    ways: Vec<BigUint>,
+}
+
+impl Chain {
+    pub fn new(joltages: &[u64], diffs: &[u64]) -> Chain {
+        let mut joltages = joltages.to_vec();
+        joltages.sort_unstable();
+        let mut chain = Chain {
+            joltages,
+            diffs: diffs.to_vec(),
+            max_diff: diffs.iter().copied().max().unwrap_or(0),
+            ways: Vec::new(),
+        };
+        // Filled in back to front, so each adapter's successors are known.
+        let n = chain.joltages.len();
+        let mut ways = vec![BigUint::zero(); n];
+        if n > 0 {
+            ways[n - 1] = BigUint::one();
+        }
+        for i in (0..n.saturating_sub(1)).rev() {
+            let sum = chain.successors(i).map(|j| &ways[j]).sum();
+            ways[i] = sum;
+        }
+        chain.ways = ways;
+        chain
+    }
+
+    // Indices that can follow `i`, whether or not they lead to the end.
+    // Joltages are sorted, so the scan stops at the largest allowed
+    // difference, which keeps `new` linear.
+    fn successors(&self, i: usize) -> impl Iterator<Item = usize> + '_ {
+        let from = self.joltages[i];
+        (i + 1..self.joltages.len())
+            .take_while(move |j| self.joltages[*j] - from <= self.max_diff)
+            .filter(move |j| self.diffs.contains(&(self.joltages[*j] - from)))
+    }
+
+    // Successors of `i` that lead to the end.
+    fn next(&self, i: usize) -> impl Iterator<Item = usize> + '_ {
+        self.successors(i).filter(move |j| !self.ways[*j].is_zero())
+    }
+
+    /// The number of arrangements.
+    pub fn count(&self) -> BigUint {
+        self.ways.first().cloned().unwrap_or_else(BigUint::zero)
+    }
+
+    /// The joltages in every arrangement; none if there are no arrangements.
+    pub fn mandatory(&self) -> Vec<u64> {
+        let total = self.count();
+        if total.is_zero() {
+            return Vec::new();
+        }
+        // ways_to[i] is the number of ways to get from the first joltage to
+        // joltages[i]; an adapter is in every arrangement if all of them pass
+        // through it.
+        let mut ways_to = vec![BigUint::zero(); self.joltages.len()];
+        ways_to[0] = BigUint::one();
+        for i in 0..self.joltages.len() {
+            let ways = ways_to[i].clone();
+            for j in self.successors(i) {
+                ways_to[j] += &ways;
+            }
+        }
+        self.joltages
+            .iter()
+            .zip(ways_to.iter().zip(self.ways.iter()))
+            .filter(|(_, (to, from))| *to * *from == total)
+            .map(|(joltage, _)| *joltage)
+            .collect()
+    }
+
+    /// Every arrangement, in lexicographic order.
+    pub fn iter(&self) -> Arrangements<'_> {
+        Arrangements {
+            chain: self,
+            path: Vec::new(),
+            started: false,
+        }
+    }
+
+    /// An arrangement chosen uniformly at random, or `None` if there are
+    /// none.
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<Vec<u64>> {
+        if self.count().is_zero() {
+            return None;
+        }
+        let mut i = 0;
+        let mut res = vec![self.joltages[0]];
+        while i + 1 < self.joltages.len() {
+            // Pick a successor in proportion to how many ways it leads on.
+            let mut r = gen_below(rng, &self.ways[i]);
+            for j in self.next(i) {
+                if r < self.ways[j] {
+                    i = j;
+                    break;
+                }
+                r -= &self.ways[j];
+            }
+            res.push(self.joltages[i]);
+        }
+        Some(res)
+    }
+}
+
+// Uniform in `0..n`, for positive `n`.
+fn gen_below<R: Rng + ?Sized>(rng: &mut R, n: &BigUint) -> BigUint {
+    let bits = n.bits();
+    #[allow(clippy::manual_div_ceil)]
+    let mut bytes = vec![0u8; ((bits + 7) / 8) as usize];
+    // Bits used in the last byte, if not all of them.
+    let top = bits % 8;
+    loop {
+        rng.fill(&mut bytes[..]);
+        if top > 0 {
+            *bytes.last_mut().unwrap() &= (1 << top) - 1;
+        }
+        let x = BigUint::from_bytes_le(&bytes);
+        if &x < n {
+            return x;
+        }
+    }
+}
+
+/// Iterator over a `Chain`'s arrangements, as joltages.
+pub struct Arrangements<'a> {
+    chain: &'a Chain,
+    // Indices of the current arrangement.
+    path: Vec<usize>,
+    started: bool,
+}
+
+impl<'a> Arrangements<'a> {
+    // Completes `path` with the first successor at each step.
+    fn descend(&mut self) {
+        while let Some(j) =
+            self.path.last().and_then(|i| self.chain.next(*i).next())
+        {
+            self.path.push(j);
+        }
+    }
+
+    // Replaces the deepest step that has a later alternative, and drops the
+    // steps after it.
+    fn advance(&mut self) -> bool {
+        while let Some(child) = self.path.pop() {
+            let parent = match self.path.last() {
+                Some(parent) => *parent,
+                None => return false,
+            };
+            if let Some(j) = self.chain.next(parent).find(|j| *j > child) {
+                self.path.push(j);
+                return true;
+            }
+        }
+        false
+    }
+}
+
+impl<'a> Iterator for Arrangements<'a> {
+    type Item = Vec<u64>;
+
+    fn next(&mut self) -> Option<Vec<u64>> {
+        if !self.started {
+            self.started = true;
+            if self.chain.count().is_zero() {
+                return None;
+            }
+            self.path.push(0);
+        } else if !self.advance() {
+            return None;
+        }
+        self.descend();
+        Some(self.path.iter().map(|i| self.chain.joltages[*i]).collect())
+    }
+}
+
+/// Consecutive joltages too far apart for any allowed difference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gap {
+    pub from: u64,
+    pub to: u64,
+}
+
+impl fmt::Display for Gap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "gap of {} jolts between {} and {}",
+            self.to - self.from,
+            self.from,
+            self.to
+        )
+    }
+}
+
+/// Differences between consecutive joltages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Gaps {
+    /// How many consecutive pairs differ by each amount.
+    pub histogram: BTreeMap<u64, usize>,
+    /// Gaps larger than the largest allowed difference.
+    pub broken: Vec<Gap>,
+}
+
+/// Scans joltages `nums`, with allowed differences `diffs`. `nums` need not
+/// be sorted.
+pub fn gaps(nums: &[u64], diffs: &[u64]) -> Gaps {
+    let max = diffs.iter().copied().max().unwrap_or(0);
+    let mut sorted = nums.to_vec();
+    sorted.sort_unstable();
+    let mut histogram = BTreeMap::new();
+    let mut broken = Vec::new();
+    for pair in sorted.windows(2) {
+        let diff = pair[1] - pair[0];
+        *histogram.entry(diff).or_insert(0) += 1;
+        if diff > max {
+            broken.push(Gap {
+                from: pair[0],
+                to: pair[1],
+            });
+        }
+    }
+    Gaps { histogram, broken }
+}
+
+/// Diagnostics for a chain using every adapter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Analysis {
+    pub gaps: Gaps,
+    /// Joltages in every arrangement (see `Chain::mandatory`).
+    pub mandatory: Vec<u64>,
+}
+
+/// Analyzes joltages `nums`, with allowed differences `diffs`. `nums` need
+/// not be sorted.
+pub fn analyze(nums: &[u64], diffs: &[u64]) -> Analysis {
+    Analysis {
+        gaps: gaps(nums, diffs),
+        mandatory: Chain::new(nums, diffs).mandatory(),
+    }
+}
+
+impl fmt::Display for Analysis {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (diff, count) in &self.gaps.histogram {
+            writeln!(f, "{} jolts: {}", diff, count)?;
+        }
+        for gap in &self.gaps.broken {
+            writeln!(f, "Broken: {}", gap)?;
+        }
+        if self.mandatory.is_empty() {
+            return writeln!(f, "Mandatory: none");
+        }
+        let mandatory: Vec<String> =
+            self.mandatory.iter().map(u64::to_string).collect();
+        writeln!(f, "Mandatory: {}", mandatory.join(" "))
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::collections::HashMap;
 
     #[test]
     fn test_part2() {
-        assert_eq!(part2(&[0, 3]), 1);
-        assert_eq!(part2(&[0, 3, 6]), 1);
-        assert_eq!(part2(&[0, 1, 3, 6]), 2);
-        assert_eq!(part2(&[0, 1, 2, 3, 6]), 4);
+        assert_eq!(part2(&[0, 3]), BigUint::from(1u32));
+        assert_eq!(part2(&[0, 3, 6]), BigUint::from(1u32));
+        assert_eq!(part2(&[0, 1, 3, 6]), BigUint::from(2u32));
+        assert_eq!(part2(&[0, 1, 2, 3, 6]), BigUint::from(4u32));
     }
 
     #[test]
@@ -101,9 +336,14 @@ mod test {
 6
 12
 4",
+            DEVICE_OFFSET,
+        );
+        assert_eq!(part1(&input).unwrap(), 35);
+        assert_eq!(
+            analyze(&input, &DIFFS).mandatory,
+            vec![0, 1, 4, 7, 10, 12, 15, 16, 19, 22]
         );
-        assert_eq!(part1(&input), 35);
-        assert_eq!(part2(&input), 8);
+        assert_eq!(part2(&input), BigUint::from(8u32));
 
         let input = parse(
             "\
@@ -138,8 +378,102 @@ mod test {
 34
 10
 3",
+            DEVICE_OFFSET,
+        );
+        assert_eq!(part1(&input).unwrap(), 220);
+        assert_eq!(part2(&input), BigUint::from(19208u32));
+    }
+
+    #[test]
+    fn test_chain() {
+        let chain = Chain::new(&[0, 1, 2, 3, 6], &DIFFS);
+        assert_eq!(
+            chain.iter().collect::<Vec<_>>(),
+            vec![
+                vec![0, 1, 2, 3, 6],
+                vec![0, 1, 3, 6],
+                vec![0, 2, 3, 6],
+                vec![0, 3, 6]
+            ]
         );
-        assert_eq!(part1(&input), 220);
-        assert_eq!(part2(&input), 19208);
+
+        // Other difference sets; 7 is a dead end.
+        let chain = Chain::new(&[0, 1, 5, 6, 7, 11], &[1, 5]);
+        assert_eq!(
+            chain.iter().collect::<Vec<_>>(),
+            vec![vec![0, 1, 6, 11], vec![0, 5, 6, 11]]
+        );
+        assert_eq!(chain.count(), BigUint::from(2u32));
+        assert_eq!(parse("1\n5\n6", 5), vec![0, 1, 5, 6, 11]);
+        assert_eq!(Chain::new(&[0, 4], &DIFFS).iter().count(), 0);
+        assert_eq!(Chain::new(&[], &DIFFS).count(), BigUint::zero());
+        assert_eq!(Chain::new(&[7], &DIFFS).iter().count(), 1);
+
+        // Counts that don't fit in a u64.
+        let joltages: Vec<u64> = (0..=200).collect();
+        let count = Chain::new(&joltages, &DIFFS).count();
+        assert!(count > BigUint::from(u64::MAX));
+        let mut ways: HashMap<u64, BigUint> = HashMap::new();
+        ways.insert(0, BigUint::one());
+        for x in 1..=200u64 {
+            let sum =
+                (x.saturating_sub(3)..x).filter_map(|y| ways.get(&y)).sum();
+            ways.insert(x, sum);
+        }
+        assert_eq!(count, ways[&200]);
+    }
+
+    #[test]
+    fn test_sample() {
+        let mut rng = StdRng::seed_from_u64(49);
+        let chain = Chain::new(&[0, 1, 2, 3, 4, 5, 8], &DIFFS);
+        let all: Vec<_> = chain.iter().collect();
+        assert_eq!(BigUint::from(all.len()), chain.count());
+        let mut seen = HashMap::new();
+        for _ in 0..1300 {
+            *seen.entry(chain.sample(&mut rng).unwrap()).or_insert(0) += 1;
+        }
+        // Each of the 13 arrangements about 100 times.
+        assert_eq!(seen.len(), all.len());
+        assert!(seen.values().all(|n| (50..150).contains(n)));
+        assert_eq!(Chain::new(&[0, 4], &DIFFS).sample(&mut rng), None);
+    }
+
+    #[test]
+    fn test_analyze() {
+        let input = parse("1\n2\n4\n5\n6", DEVICE_OFFSET);
+        let analysis = analyze(&input, &DIFFS);
+        assert_eq!(
+            analysis.gaps.histogram.into_iter().collect::<Vec<_>>(),
+            vec![(1, 4), (2, 1), (3, 1)]
+        );
+        assert!(analysis.gaps.broken.is_empty());
+        assert_eq!(analysis.mandatory, vec![0, 6, 9]);
+        assert_eq!(part1(&input).unwrap(), 4);
+
+        let input = parse("1\n2\n7", DEVICE_OFFSET);
+        let analysis = analyze(&input, &DIFFS);
+        assert_eq!(analysis.gaps.broken, vec![Gap { from: 2, to: 7 }]);
+        assert!(analysis.mandatory.is_empty());
+        assert_eq!(analyze(&[10, 7, 0, 2, 1], &DIFFS), analysis);
+        assert_eq!(part1(&[6, 0, 3, 4]).unwrap(), 1);
+        assert_eq!(
+            analysis.to_string(),
+            "\
+1 jolts: 2
+3 jolts: 1
+5 jolts: 1
+Broken: gap of 5 jolts between 2 and 7
+Mandatory: none
+"
+        );
+        assert_eq!(
+            part1(&input).unwrap_err().to_string(),
+            "Chain broken: gap of 5 jolts between 2 and 7"
+        );
+
+        // With other differences, a gap of 2 is broken.
+        let analysis = analyze(&[0, 1, 3, 4], &[1]);
+        assert_eq!(analysis.gaps.broken, vec![Gap { from: 1, to: 3 }]);
     }
 }
@@ -0,0 +1,561 @@
+//! A generic Conway-style cellular automaton, shared by days 11, 17, and 24.
+//!
+//! An automaton is a `Topology`, which says which cells exist and which are
+//! neighbors; a birth/survival `Rule`; and a storage backend. `Sparse` stores
+//! only live cells, and works for unbounded topologies. `Dense` stores every
+//! cell of a bounded topology, with each cell's neighbors precomputed.
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+use std::str::FromStr;
+
+/// Which neighbor counts cause a dead cell to be born, or a live cell to
+/// survive. Parsed from the usual `B3/S23` notation.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Rule {
+    birth: Vec<usize>,
+    survival: Vec<usize>,
+}
+
+impl Rule {
+    pub fn new(birth: &[usize], survival: &[usize]) -> Rule {
+        let mut birth = birth.to_vec();
+        let mut survival = survival.to_vec();
+        birth.sort_unstable();
+        birth.dedup();
+        survival.sort_unstable();
+        survival.dedup();
+        Rule { birth, survival }
+    }
+
+    /// Whether a cell is alive in the next generation.
+    pub fn next(&self, alive: bool, neighbor_count: usize) -> bool {
+        if alive {
+            self.survival.contains(&neighbor_count)
+        } else {
+            self.birth.contains(&neighbor_count)
+        }
+    }
+}
+
+// Counts are single digits, as in `B3/S23`, unless separated by commas, as
+// in `B3/S2,3,12`. A lone multi-digit count needs a trailing comma: `S12,`.
+fn parse_counts(s: &str) -> Result<Vec<usize>, Box<dyn Error>> {
+    if s.contains(',') {
+        s.split(',')
+            .filter(|n| !n.is_empty())
+            .map(|n| n.parse().map_err(|e| format!("Bad count {}: {}", n, e)))
+            .collect::<Result<_, _>>()
+            .map_err(|e| e.into())
+    } else {
+        s.chars()
+            .map(|c| {
+                c.to_digit(10)
+                    .map(|d| d as usize)
+                    .ok_or_else(|| format!("Bad count {}", c))
+            })
+            .collect::<Result<_, _>>()
+            .map_err(|e| e.into())
+    }
+}
+
+impl FromStr for Rule {
+    type Err = Box<dyn Error>;
+    /// ```
+    /// use aoc2020::automaton::Rule;
+    /// let rule: Rule = "B3/S23".parse().unwrap();
+    /// assert!(rule.next(false, 3));
+    /// assert!(!rule.next(false, 2));
+    /// assert!(rule.next(true, 2));
+    /// assert!(!rule.next(true, 4));
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (birth, survival) = s
+            .split_once('/')
+            .ok_or_else(|| format!("Missing '/': {}", s))?;
+        let birth = birth
+            .strip_prefix('B')
+            .ok_or_else(|| format!("Missing 'B': {}", s))?;
+        let survival = survival
+            .strip_prefix('S')
+            .ok_or_else(|| format!("Missing 'S': {}", s))?;
+        Ok(Rule::new(&parse_counts(birth)?, &parse_counts(survival)?))
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let counts = |v: &[usize]| {
+            let strings: Vec<_> = v.iter().map(|n| n.to_string()).collect();
+            if v.iter().all(|n| *n <= 9) {
+                strings.join("")
+            } else if v.len() == 1 {
+                format!("{},", strings[0])
+            } else {
+                strings.join(",")
+            }
+        };
+        write!(f, "B{}/S{}", counts(&self.birth), counts(&self.survival))
+    }
+}
+
+/// The space an automaton lives in.
+pub trait Topology {
+    type Cell: Copy + Eq + Hash;
+
+    /// Appends the neighbors of `cell` to `out`.
+    fn neighbors(&self, cell: &Self::Cell, out: &mut Vec<Self::Cell>);
+
+    /// Every cell, for bounded topologies; `None` for unbounded ones.
+    fn cells(&self) -> Option<Vec<Self::Cell>>;
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Neighborhood {
+    /// Cells differing by at most 1 in every dimension.
+    Moore,
+    /// Cells differing by 1 in exactly one dimension.
+    VonNeumann,
+}
+
+/// An `N`-dimensional square grid, optionally bounded.
+pub struct Square<const N: usize> {
+    deltas: Vec<[i32; N]>,
+    /// Inclusive lower and exclusive upper bound of each dimension.
+    bounds: Option<([i32; N], [i32; N])>,
+}
+
+impl<const N: usize> Square<N> {
+    pub fn new(neighborhood: Neighborhood) -> Square<N> {
+        // Count through all 3^N combinations of -1, 0, and 1.
+        let mut deltas = Vec::new();
+        for i in 0..3usize.pow(N as u32) {
+            let mut delta = [0; N];
+            let mut rem = i;
+            for d in delta.iter_mut() {
+                *d = (rem % 3) as i32 - 1;
+                rem /= 3;
+            }
+            let nonzero = delta.iter().filter(|d| **d != 0).count();
+            let include = match neighborhood {
+                Neighborhood::Moore => nonzero > 0,
+                Neighborhood::VonNeumann => nonzero == 1,
+            };
+            if include {
+                deltas.push(delta);
+            }
+        }
+        Square {
+            deltas,
+            bounds: None,
+        }
+    }
+
+    /// Restricts the grid to `min[d] <= cell[d] < max[d]` in each dimension.
+    pub fn bounded(mut self, min: [i32; N], max: [i32; N]) -> Square<N> {
+        self.bounds = Some((min, max));
+        self
+    }
+
+    pub fn deltas(&self) -> &[[i32; N]] {
+        &self.deltas
+    }
+
+    fn in_bounds(&self, cell: &[i32; N]) -> bool {
+        match &self.bounds {
+            None => true,
+            Some((min, max)) => {
+                (0..N).all(|d| (min[d]..max[d]).contains(&cell[d]))
+            }
+        }
+    }
+}
+
+impl<const N: usize> Topology for Square<N> {
+    type Cell = [i32; N];
+
+    fn neighbors(&self, cell: &[i32; N], out: &mut Vec<[i32; N]>) {
+        for delta in &self.deltas {
+            let mut neighbor = *cell;
+            for d in 0..N {
+                neighbor[d] += delta[d];
+            }
+            if self.in_bounds(&neighbor) {
+                out.push(neighbor);
+            }
+        }
+    }
+
+    fn cells(&self) -> Option<Vec<[i32; N]>> {
+        let (min, max) = self.bounds?;
+        let mut res = vec![min];
+        for d in 0..N {
+            res = res
+                .into_iter()
+                .flat_map(|cell| {
+                    (min[d]..max[d]).map(move |i| {
+                        let mut cell = cell;
+                        cell[d] = i;
+                        cell
+                    })
+                })
+                .collect();
+        }
+        Some(res)
+    }
+}
+
+/// An unbounded hexagonal grid, in axial coordinates. Moving east is (1, 0)
+/// and moving north-east is (1, 1).
+pub struct Hex;
+
+impl Hex {
+    pub const DIRECTIONS: [(i32, i32); 6] =
+        [(-1, 0), (1, 0), (0, 1), (1, 1), (-1, -1), (0, -1)];
+}
+
+impl Topology for Hex {
+    type Cell = (i32, i32);
+
+    fn neighbors(&self, cell: &(i32, i32), out: &mut Vec<(i32, i32)>) {
+        for d in &Hex::DIRECTIONS {
+            out.push((cell.0 + d.0, cell.1 + d.1));
+        }
+    }
+
+    fn cells(&self) -> Option<Vec<(i32, i32)>> {
+        None
+    }
+}
+
+/// Operations common to all storage backends.
+pub trait Automaton {
+    type Cell;
+
+    /// Advances one generation. Returns whether anything changed.
+    fn step(&mut self) -> bool;
+
+    /// Number of live cells.
+    fn population(&self) -> usize;
+
+    fn is_alive(&self, cell: &Self::Cell) -> bool;
+
+    /// Live cells, in no particular order.
+    fn live_cells(&self) -> Vec<Self::Cell>;
+
+    fn run(&mut self, generations: usize) {
+        for _ in 0..generations {
+            self.step();
+        }
+    }
+
+    /// Steps until a generation doesn't change anything. Returns the number
+    /// of generations that did.
+    fn run_until_stable(&mut self) -> usize {
+        let mut generations = 0;
+        while self.step() {
+            generations += 1;
+        }
+        generations
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Backend {
+    Sparse,
+    Dense,
+}
+
+/// Creates an automaton with the given backend.
+pub fn new<T, I>(
+    backend: Backend,
+    topology: T,
+    rule: Rule,
+    live: I,
+) -> Result<Box<dyn Automaton<Cell = T::Cell>>, Box<dyn Error>>
+where
+    T: Topology + 'static,
+    I: IntoIterator<Item = T::Cell>,
+{
+    Ok(match backend {
+        Backend::Sparse => Box::new(Sparse::new(topology, rule, live)?),
+        Backend::Dense => Box::new(Dense::new(topology, rule, live)?),
+    })
+}
+
+/// Stores only the live cells.
+pub struct Sparse<T: Topology> {
+    topology: T,
+    rule: Rule,
+    live: HashSet<T::Cell>,
+    // Every cell; only needed for rules that give birth to cells with no
+    // neighbors.
+    all_cells: Option<Vec<T::Cell>>,
+}
+
+impl<T: Topology> Sparse<T> {
+    pub fn new<I: IntoIterator<Item = T::Cell>>(
+        topology: T,
+        rule: Rule,
+        live: I,
+    ) -> Result<Sparse<T>, Box<dyn Error>> {
+        let all_cells = if rule.next(false, 0) {
+            Some(topology.cells().ok_or_else(|| {
+                format!("{} would fill an unbounded topology", rule)
+            })?)
+        } else {
+            None
+        };
+        Ok(Sparse {
+            topology,
+            rule,
+            live: live.into_iter().collect(),
+            all_cells,
+        })
+    }
+}
+
+impl<T: Topology> Automaton for Sparse<T> {
+    type Cell = T::Cell;
+
+    fn step(&mut self) -> bool {
+        let mut neighbor_counts = HashMap::<T::Cell, usize>::new();
+        let mut neighbors = Vec::new();
+        for cell in &self.live {
+            neighbors.clear();
+            self.topology.neighbors(cell, &mut neighbors);
+            for neighbor in &neighbors {
+                *neighbor_counts.entry(*neighbor).or_insert(0) += 1;
+            }
+        }
+
+        // Cells with no live neighbors don't show up in `neighbor_counts`,
+        // but may still survive, or (if we have them) be born.
+        let mut next = HashSet::new();
+        let zero_neighbor_cells = self
+            .live
+            .iter()
+            .chain(self.all_cells.iter().flatten())
+            .filter(|cell| !neighbor_counts.contains_key(cell));
+        for cell in zero_neighbor_cells {
+            if self.rule.next(self.live.contains(cell), 0) {
+                next.insert(*cell);
+            }
+        }
+        for (cell, count) in &neighbor_counts {
+            if self.rule.next(self.live.contains(cell), *count) {
+                next.insert(*cell);
+            }
+        }
+
+        let changed = next != self.live;
+        self.live = next;
+        changed
+    }
+
+    fn population(&self) -> usize {
+        self.live.len()
+    }
+
+    fn is_alive(&self, cell: &T::Cell) -> bool {
+        self.live.contains(cell)
+    }
+
+    fn live_cells(&self) -> Vec<T::Cell> {
+        self.live.iter().copied().collect()
+    }
+}
+
+/// Stores every cell of a bounded topology.
+pub struct Dense<T: Topology> {
+    rule: Rule,
+    cells: Vec<T::Cell>,
+    index: HashMap<T::Cell, usize>,
+    // Indexes of each cell's neighbors.
+    neighbors: Vec<Vec<usize>>,
+    alive: Vec<bool>,
+}
+
+impl<T: Topology> Dense<T> {
+    pub fn new<I: IntoIterator<Item = T::Cell>>(
+        topology: T,
+        rule: Rule,
+        live: I,
+    ) -> Result<Dense<T>, Box<dyn Error>> {
+        let cells = topology
+            .cells()
+            .ok_or("Dense backend needs a bounded topology")?;
+        let index: HashMap<T::Cell, usize> =
+            cells.iter().enumerate().map(|(i, c)| (*c, i)).collect();
+
+        let mut buf = Vec::new();
+        let neighbors = cells
+            .iter()
+            .map(|cell| {
+                buf.clear();
+                topology.neighbors(cell, &mut buf);
+                buf.iter().filter_map(|n| index.get(n).copied()).collect()
+            })
+            .collect();
+
+        let mut alive = vec![false; cells.len()];
+        for cell in live {
+            let i = index.get(&cell).ok_or("Live cell out of bounds")?;
+            alive[*i] = true;
+        }
+        Ok(Dense {
+            rule,
+            cells,
+            index,
+            neighbors,
+            alive,
+        })
+    }
+}
+
+impl<T: Topology> Dense<T> {
+    /// Whether each cell is alive, in the order of `Topology::cells`.
+    pub fn state(&self) -> &[bool] {
+        &self.alive
+    }
+}
+
+impl<T: Topology> Automaton for Dense<T> {
+    type Cell = T::Cell;
+
+    fn step(&mut self) -> bool {
+        let next: Vec<bool> = self
+            .neighbors
+            .iter()
+            .zip(self.alive.iter())
+            .map(|(neighbors, alive)| {
+                let count =
+                    neighbors.iter().filter(|n| self.alive[**n]).count();
+                self.rule.next(*alive, count)
+            })
+            .collect();
+        let changed = next != self.alive;
+        self.alive = next;
+        changed
+    }
+
+    fn population(&self) -> usize {
+        self.alive.iter().filter(|a| **a).count()
+    }
+
+    fn is_alive(&self, cell: &T::Cell) -> bool {
+        self.index.get(cell).map(|i| self.alive[*i]) == Some(true)
+    }
+
+    fn live_cells(&self) -> Vec<T::Cell> {
+        self.cells
+            .iter()
+            .zip(self.alive.iter())
+            .filter(|(_, alive)| **alive)
+            .map(|(cell, _)| *cell)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rule() {
+        let rule: Rule = "B3/S23".parse().unwrap();
+        assert_eq!(rule, Rule::new(&[3], &[3, 2]));
+        assert_eq!(rule.to_string(), "B3/S23");
+        assert_eq!("B0/S".parse::<Rule>().unwrap(), Rule::new(&[0], &[]));
+        let rule: Rule = "B3,10/S2,3".parse().unwrap();
+        assert!(rule.next(false, 10));
+        assert!(!rule.next(false, 1));
+        assert_eq!(rule.to_string(), "B3,10/S23");
+        let rule: Rule = "B/S12,".parse().unwrap();
+        assert!(rule.next(true, 12));
+        assert_eq!(rule.to_string(), "B/S12,");
+        assert!("B3S23".parse::<Rule>().is_err());
+        assert!("S23/B3".parse::<Rule>().is_err());
+        assert!("Bx/S23".parse::<Rule>().is_err());
+    }
+
+    #[test]
+    fn test_square() {
+        assert_eq!(Square::<2>::new(Neighborhood::Moore).deltas().len(), 8);
+        assert_eq!(Square::<4>::new(Neighborhood::Moore).deltas().len(), 80);
+        assert_eq!(
+            Square::<3>::new(Neighborhood::VonNeumann).deltas().len(),
+            6
+        );
+
+        let square = Square::<2>::new(Neighborhood::Moore);
+        assert!(square.cells().is_none());
+        let square = square.bounded([0, 0], [2, 3]);
+        assert_eq!(square.cells().unwrap().len(), 6);
+        let mut neighbors = Vec::new();
+        square.neighbors(&[0, 0], &mut neighbors);
+        neighbors.sort_unstable();
+        assert_eq!(neighbors, vec![[0, 1], [1, 0], [1, 1]]);
+    }
+
+    #[test]
+    fn test_backends() {
+        // A blinker oscillates, so never stabilizes, but keeps its
+        // population.
+        let blinker = vec![[1, 0], [1, 1], [1, 2]];
+        for backend in &[Backend::Sparse, Backend::Dense] {
+            let square =
+                Square::<2>::new(Neighborhood::Moore).bounded([-5, -5], [5, 5]);
+            let mut automaton = new(
+                *backend,
+                square,
+                "B3/S23".parse().unwrap(),
+                blinker.clone(),
+            )
+            .unwrap();
+            assert!(automaton.step());
+            let mut live = automaton.live_cells();
+            live.sort_unstable();
+            assert_eq!(live, vec![[0, 1], [1, 1], [2, 1]]);
+            assert!(automaton.is_alive(&[0, 1]));
+            assert!(!automaton.is_alive(&[1, 0]));
+            assert!(!automaton.is_alive(&[100, 100]));
+            automaton.run(11);
+            assert_eq!(automaton.population(), 3);
+            assert!(automaton.is_alive(&[1, 0]));
+        }
+
+        // A block is stable.
+        let block = vec![[0, 0], [0, 1], [1, 0], [1, 1]];
+        let mut automaton = Sparse::new(
+            Square::<2>::new(Neighborhood::Moore),
+            "B3/S23".parse().unwrap(),
+            block,
+        )
+        .unwrap();
+        assert_eq!(automaton.run_until_stable(), 0);
+        assert_eq!(automaton.population(), 4);
+    }
+
+    #[test]
+    fn test_birth_from_nothing() {
+        let rule: Rule = "B0/S".parse().unwrap();
+        assert!(Sparse::new(Hex, rule.clone(), vec![]).is_err());
+        assert!(Dense::new(Hex, rule.clone(), vec![]).is_err());
+
+        // Everything is born, then everything dies.
+        let square = || Square::<1>::new(Neighborhood::Moore).bounded([0], [3]);
+        let mut sparse = Sparse::new(square(), rule.clone(), vec![]).unwrap();
+        let mut dense = Dense::new(square(), rule, vec![]).unwrap();
+        sparse.step();
+        dense.step();
+        assert_eq!(sparse.population(), 3);
+        assert_eq!(dense.population(), 3);
+        sparse.step();
+        dense.step();
+        assert_eq!(sparse.population(), 0);
+        assert_eq!(dense.population(), 0);
+    }
+}
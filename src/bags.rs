@@ -79,7 +79,7 @@ fn build_inner_to_outer_map(
 }
 
 pub fn number_of_outer_bags_that_could_have_shiny(rules: &[Rule]) -> usize {
-    let inner_to_outer_map = build_inner_to_outer_map(&rules);
+    let inner_to_outer_map = build_inner_to_outer_map(rules);
     let start_point = BagColor("shiny gold".into());
     let mut to_visit = vec![&start_point];
     let mut visited = HashSet::<&BagColor>::new();
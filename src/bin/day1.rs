@@ -1,22 +1,22 @@
-use aoc2020::factors::find_sum_factors;
+use aoc2020::factors::KSum;
 
 fn main() {
     use std::io::prelude::*;
-    let n: u32 = std::env::args()
+    let n: usize = std::env::args()
         .nth(1)
         .expect("missing n")
         .parse()
         .expect("parsing n");
-    let target: i32 = std::env::args()
+    let target: i64 = std::env::args()
         .nth(2)
         .expect("missing target")
         .parse()
         .expect("parsing target");
-    let xs: Vec<i32> = std::io::stdin()
+    let xs: Vec<i64> = std::io::stdin()
         .lock()
         .lines()
         .map(|s| s.unwrap().parse().expect("parsing line"))
         .collect();
-    let factors = find_sum_factors(n, xs.as_slice(), target).unwrap();
-    println!("{}", factors.iter().product::<i32>());
+    let factors = KSum::new(&xs).unwrap().find(n, target).unwrap();
+    println!("{}", factors.iter().product::<i64>());
 }
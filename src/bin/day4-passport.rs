@@ -1,170 +1,28 @@
-use aoc2020::BufReadSplitOnBlank;
+use aoc2020::passport::{key_val_lines_to_hashmap, Passport, Schema};
+use aoc2020::records::read_records;
 use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::error::Error;
 
-pub fn parse_key_val(s: &str) -> (&str, &str) {
-    let mut key_val_seq = s.splitn(2, ':');
-    // XXX: Yuck.
-    let key = key_val_seq.next().unwrap_or("");
-    let val = key_val_seq.next().unwrap_or("");
-    assert!(key_val_seq.next().is_none());
-    (key, val)
-}
-
-#[cfg(test)]
-#[test]
-fn test_parse_key_val() {
-    assert_eq!(parse_key_val("key:val"), ("key", "val"));
-    assert_eq!(parse_key_val("key:"), ("key", ""));
-    assert_eq!(parse_key_val("key"), ("key", ""));
-    assert_eq!(parse_key_val(""), ("", ""));
-}
+/// A record with all the required fields, whatever their values; for part 1.
+pub struct CompleteRecord {}
 
-pub fn key_val_lines_to_hashmap<'a, I: Iterator<Item = &'a str>>(
-    it: I,
-) -> HashMap<String, String> {
-    // Convert vector of lines into a flat iterator over tokens
-    let tokens = it.map(|l| l.split_ascii_whitespace()).flatten();
-
-    // Convert tokens to a hashmap
-    tokens
-        .map(parse_key_val)
-        .map(|(k, v)| (k.to_string(), v.to_string()))
-        .collect()
-}
-
-#[cfg(test)]
-#[test]
-fn test_parse_key_val_lines_to_hashmap() {
-    assert_eq!(
-        key_val_lines_to_hashmap(["k1:v1 k2:v2", "k3:v3"].iter().copied()),
-        [
-            ("k1".to_string(), "v1".to_string()),
-            ("k2".to_string(), "v2".to_string()),
-            ("k3".to_string(), "v3".to_string())
-        ]
-        .iter()
-        .cloned()
-        .collect()
-    );
-}
-
-/// Represent a passport. For this part we don't actually need to store anything yet.
-pub struct Passport {}
-
-/// Trait to create a passport from a set of key/value pairs.
-impl TryFrom<HashMap<String, String>> for Passport {
+/// Trait to create a record from a set of key/value pairs.
+impl TryFrom<HashMap<String, String>> for CompleteRecord {
     type Error = String;
     fn try_from(value: HashMap<String, String>) -> Result<Self, Self::Error> {
-        for key in ["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid"].iter() {
-            if !value.contains_key(*key) {
-                return Err(format!("Missing key {}", key));
-            }
-        }
-        Ok(Passport {})
-    }
-}
-
-/// Stricter passport, for part 2.
-pub struct StrictPassport {}
-
-fn parse_int_field(
-    name: &str,
-    s: &str,
-    min: i32,
-    max: i32,
-) -> Result<i32, Box<dyn Error>> {
-    let i: i32 = s.parse()?;
-    if !(i >= min && i <= max) {
-        return Err(format!("{}={} out of range", name, i).into());
-    }
-    Ok(i)
-}
-
-/// Trait to create a passport from a set of key/value pairs.
-impl TryFrom<HashMap<String, String>> for StrictPassport {
-    type Error = Box<dyn Error>;
-    fn try_from(value: HashMap<String, String>) -> Result<Self, Self::Error> {
-        parse_int_field(
-            "byr",
-            value.get("byr").ok_or("Missing byr")?,
-            1920,
-            2002,
-        )?;
-        parse_int_field(
-            "iyr",
-            value.get("iyr").ok_or("Missing iyr")?,
-            2010,
-            2020,
-        )?;
-        parse_int_field(
-            "eyr",
-            value.get("eyr").ok_or("Missing eyr")?,
-            2020,
-            2030,
-        )?;
-
-        // Check hgt
-        {
-            let hgt = value.get("hgt").ok_or("Missing hgt")?;
-            if hgt.len() < 2 {
-                return Err(format!("Bad hgt {}", hgt).into());
-            }
-            let (value, unit) = hgt.split_at(hgt.len() - 2);
-            let value: i32 = value.parse()?;
-            let range = match unit {
-                "in" => (59..=76),
-                "cm" => (150..=193),
-                _ => return Err(format!("hgt bad unit {}", unit).into()),
-            };
-            if !range.contains(&value) {
-                return Err(
-                    format!("hgt {}{} out of range", value, unit).into()
-                );
-            }
+        match Schema::day4().validate_presence(&value).first() {
+            Some(violation) => Err(violation.to_string()),
+            None => Ok(CompleteRecord {}),
         }
-
-        // Check hcl
-        {
-            let hcl = value.get("hcl").ok_or("Missing hcl")?;
-            if !(hcl.starts_with('#')) {
-                return Err(format!("hcl missing #: {}", hcl).into());
-            }
-            let (_, val) = hcl.split_at(1);
-            if !(val.len() == 6 && val.chars().all(|c| c.is_digit(16))) {
-                return Err(format!("bad hcl val: {}", val).into());
-            }
-        }
-
-        // Check ecl
-        {
-            let ecl = value.get("ecl").ok_or("Missing ecl")?;
-            if !["amb", "blu", "brn", "gry", "grn", "hzl", "oth"]
-                .contains(&ecl.as_str())
-            {
-                return Err(format!("bad ecl: {}", ecl).into());
-            }
-        }
-
-        // Check pid
-        {
-            let pid = value.get("pid").ok_or("Missing pid")?;
-            if !(pid.len() == 9 && pid.chars().all(|c| c.is_digit(10))) {
-                return Err(format!("bad pid: {}", pid).into());
-            }
-        }
-
-        Ok(StrictPassport {})
     }
 }
 
 #[cfg(test)]
 #[test]
 fn test_parse_strict_passport() {
-    let parse = |s| {
-        StrictPassport::try_from(key_val_lines_to_hashmap([s].iter().copied()))
-    };
+    let parse =
+        |s| Passport::try_from(key_val_lines_to_hashmap([s].iter().copied()));
     parse(
         "pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980 hcl:#623a2f",
     )
@@ -207,8 +65,10 @@ hcl:#cfa07d eyr:2025 pid:166559648
 iyr:2011 ecl:brn hgt:59in
  ";
     assert_eq!(
-        count_valid_passports::<Passport, _>(Cursor::new(input.as_bytes()))
-            .unwrap(),
+        count_valid_passports::<CompleteRecord, _>(Cursor::new(
+            input.as_bytes()
+        ))
+        .unwrap(),
         2
     );
 }
@@ -219,20 +79,16 @@ pub fn count_valid_passports<
 >(
     input: R,
 ) -> Result<usize, Box<dyn Error>> {
-    // Try converting each chunk of text (separated by blank lines) into a passport, counting
-    // the number of valid conversions.
-    BufReadSplitOnBlank::new(input).try_fold(
+    // Try converting each record (separated by blank lines) into a passport, counting the number
+    // of valid conversions.
+    read_records(input).try_fold(
         0,
-        |acc, lines| -> Result<usize, Box<dyn Error>> {
+        |acc, record| -> Result<usize, Box<dyn Error>> {
             // Bubble up IO errors.
-            let lines = lines?;
-
-            // Convert vector of lines into a flat iterator over tokens
-            let tokens =
-                lines.iter().map(|l| l.split_ascii_whitespace()).flatten();
+            let record = record?;
 
-            // Convert tokens to a hashmap
-            let map = key_val_lines_to_hashmap(tokens);
+            // Convert lines to a hashmap
+            let map = key_val_lines_to_hashmap(record.lines());
 
             // Add to running total iff can be converted into a passport.
             Ok(acc
@@ -247,8 +103,8 @@ pub fn count_valid_passports<
 fn main() {
     let part = std::env::args().nth(1).expect("missing part");
     let fun = match part.as_str() {
-        "a" => count_valid_passports::<Passport, _>,
-        "b" => count_valid_passports::<StrictPassport, _>,
+        "a" => count_valid_passports::<CompleteRecord, _>,
+        "b" => count_valid_passports::<Passport, _>,
         _ => panic!("Bad part {}", part),
     };
     println!("{}", fun(std::io::stdin().lock()).unwrap());
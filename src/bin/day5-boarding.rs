@@ -1,3 +1,4 @@
+use aoc2020::boarding::SeatLayout;
 use std::error::Error;
 
 #[cfg(test)]
@@ -8,15 +9,8 @@ fn test_seat_num() {
     assert_eq!(seat_num("BBFFBBFRLL").unwrap(), 820);
 }
 
-fn seat_num(s: &str) -> Result<usize, std::num::ParseIntError> {
-    let s = s.chars().map(|x| match x {
-        'F' => '0',
-        'B' => '1',
-        'L' => '0',
-        'R' => '1',
-        x => x,
-    });
-    usize::from_str_radix(s.collect::<String>().as_str(), 2)
+fn seat_num(s: &str) -> Result<usize, Box<dyn Error>> {
+    Ok(SeatLayout::DAY5.decode(s)?.id)
 }
 
 #[cfg(test)]
@@ -33,10 +27,9 @@ fn test_highest_seat_id() {
 pub fn highest_seat_id<R: std::io::BufRead>(
     reader: R,
 ) -> Result<usize, Box<dyn Error>> {
-    let mut result_nums =
-        reader.lines().map(|l| -> Result<usize, Box<dyn Error>> {
-            Ok(seat_num(l?.as_str())?)
-        });
+    let mut result_nums = reader
+        .lines()
+        .map(|l| -> Result<usize, Box<dyn Error>> { seat_num(l?.as_str()) });
     result_nums
         .try_fold(0, |acc, result_num| Ok(std::cmp::max(acc, result_num?)))
 }
@@ -60,9 +53,7 @@ pub fn missing_seat_id<R: std::io::BufRead>(
 ) -> Result<usize, Box<dyn Error>> {
     let nums: Result<Vec<usize>, Box<dyn Error>> = reader
         .lines()
-        .map(|l| -> Result<usize, Box<dyn Error>> {
-            Ok(seat_num(l?.as_str())?)
-        })
+        .map(|l| -> Result<usize, Box<dyn Error>> { seat_num(l?.as_str()) })
         .collect();
     let mut nums = nums?;
     nums.sort_unstable();
@@ -74,8 +65,23 @@ pub fn missing_seat_id<R: std::io::BufRead>(
     Err("Not found".into())
 }
 
+pub fn seat_map<R: std::io::BufRead>(
+    reader: R,
+) -> Result<String, Box<dyn Error>> {
+    let layout = SeatLayout::DAY5;
+    let seats = reader
+        .lines()
+        .map(|l| layout.decode(l?.as_str()))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(layout.seat_map(&seats))
+}
+
 fn main() {
     let part = std::env::args().nth(1).expect("missing part");
+    if part == "map" {
+        print!("{}", seat_map(std::io::stdin().lock()).unwrap());
+        return;
+    }
     let fun = match part.as_str() {
         "a" => highest_seat_id,
         "b" => missing_seat_id,
@@ -1,19 +1,51 @@
 use aoc2020::passwords;
+use std::error::Error;
 
+type CheckLine = fn(&str) -> Result<bool, Box<dyn Error>>;
+type Audit = fn(
+    Box<dyn Iterator<Item = String>>,
+) -> Result<Vec<passwords::Violation>, Box<dyn Error>>;
+
+// With a second argument of `text` or `json`, prints a report of the failing
+// lines instead of counting the passing ones.
 fn main() {
     let policy_arg = std::env::args().nth(1).expect("missing policy");
-    let check_line = match policy_arg.as_str() {
-        "old" => passwords::check_line::<passwords::LegacyPasswordPolicy>,
-        "new" => passwords::check_line::<passwords::NewPasswordPolicy>,
+    let (check_line, audit): (CheckLine, Audit) = match policy_arg.as_str() {
+        "old" => (
+            passwords::check_line::<passwords::LegacyPasswordPolicy>,
+            passwords::audit::<passwords::LegacyPasswordPolicy, String>,
+        ),
+        "new" => (
+            passwords::check_line::<passwords::NewPasswordPolicy>,
+            passwords::audit::<passwords::NewPasswordPolicy, String>,
+        ),
+        // Each line's policy is written in the `passwords::Policy` language.
+        "spec" => (
+            passwords::check_line::<passwords::Policy>,
+            passwords::audit::<passwords::Policy, String>,
+        ),
         _ => panic!("Unknown policy"),
     };
     use std::io::prelude::*;
-    println!(
-        "{}",
-        std::io::stdin()
-            .lock()
-            .lines()
-            .filter(|line| check_line(line.as_ref().unwrap().as_str()).unwrap())
-            .count()
-    );
+    let lines = std::io::stdin().lock().lines();
+    match std::env::args().nth(2).as_deref() {
+        None => println!(
+            "{}",
+            lines
+                .filter(
+                    |line| check_line(line.as_ref().unwrap().as_str()).unwrap()
+                )
+                .count()
+        ),
+        Some(format) => {
+            // Streams the lines, keeping only the failing ones.
+            let violations =
+                audit(Box::new(lines.map(|line| line.unwrap()))).unwrap();
+            match format {
+                "text" => print!("{}", passwords::report_text(&violations)),
+                "json" => println!("{}", passwords::report_json(&violations)),
+                _ => panic!("Unknown report format"),
+            }
+        }
+    }
 }
@@ -1,19 +1,30 @@
 use aoc2020::toboggan as t;
 use std::io::Read;
 
+// Takes one or more right/down pairs; with several, also prints the product.
 fn main() {
-    let right: usize = std::env::args()
-        .nth(1)
-        .expect("Missing right")
-        .parse()
-        .expect("Parsing right");
-    let down: usize = std::env::args()
-        .nth(2)
-        .expect("Missing down")
-        .parse()
-        .expect("Parsing down");
+    let args: Vec<usize> = std::env::args()
+        .skip(1)
+        .map(|arg| arg.parse().expect("Parsing right/down"))
+        .collect();
+    if args.is_empty() || args.len() % 2 == 1 {
+        panic!("Expected right/down pairs");
+    }
+    let angles: Vec<t::Angle> = args
+        .chunks(2)
+        .map(|pair| t::Angle {
+            right: pair[0],
+            down: pair[1],
+        })
+        .collect();
     let mut map_string = String::new();
     std::io::stdin().read_to_string(&mut map_string).unwrap();
     let map: t::Map = map_string.parse().unwrap();
-    println!("{}", t::trees_for_angle(&map, t::Angle { right, down }));
+    let counts = t::trees_for_angles(&map, &angles);
+    for count in &counts {
+        println!("{}", count);
+    }
+    if counts.len() > 1 {
+        println!("{}", counts.iter().product::<usize>());
+    }
 }
@@ -0,0 +1,260 @@
+use std::collections::HashSet;
+use std::error::Error;
+
+/// A plane's seats, and the letters that pick the lower or upper half of the
+/// remaining rows or columns in a boarding pass. Row and column counts don't
+/// need to be powers of two; passes just use enough letters for the largest
+/// index. Built with `new`, which checks that there's at least one seat.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeatLayout {
+    rows: usize,
+    cols: usize,
+    /// (lower, upper)
+    row_letters: (char, char),
+    /// (lower, upper)
+    col_letters: (char, char),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Seat {
+    pub row: usize,
+    pub col: usize,
+    /// `row * cols + col`
+    pub id: usize,
+}
+
+// Number of halvings needed to get down to one of `n` choices.
+fn bits_for(n: usize) -> usize {
+    (usize::BITS - n.saturating_sub(1).leading_zeros()) as usize
+}
+
+fn encode_bits(value: usize, bits: usize, letters: (char, char)) -> String {
+    (0..bits)
+        .rev()
+        .map(|bit| {
+            if value & (1 << bit) == 0 {
+                letters.0
+            } else {
+                letters.1
+            }
+        })
+        .collect()
+}
+
+fn decode_bits(
+    s: &[char],
+    letters: (char, char),
+) -> Result<usize, Box<dyn Error>> {
+    s.iter().try_fold(0, |acc, c| match *c {
+        c if c == letters.0 => Ok(acc << 1),
+        c if c == letters.1 => Ok(acc << 1 | 1),
+        c => Err(format!(
+            "Bad letter {:?}, expected {:?} or {:?}",
+            c, letters.0, letters.1
+        )
+        .into()),
+    })
+}
+
+impl SeatLayout {
+    /// The day 5 plane: 128 rows of 8 seats, as in `FBFBBFFRLR`.
+    pub const DAY5: SeatLayout = SeatLayout {
+        rows: 128,
+        cols: 8,
+        row_letters: ('F', 'B'),
+        col_letters: ('L', 'R'),
+    };
+
+    pub fn new(
+        rows: usize,
+        cols: usize,
+        row_letters: (char, char),
+        col_letters: (char, char),
+    ) -> Result<SeatLayout, Box<dyn Error>> {
+        if rows == 0 || cols == 0 {
+            return Err(format!("Empty layout {}x{}", rows, cols).into());
+        }
+        if row_letters.0 == row_letters.1 || col_letters.0 == col_letters.1 {
+            return Err("Lower and upper letters must differ".into());
+        }
+        Ok(SeatLayout {
+            rows,
+            cols,
+            row_letters,
+            col_letters,
+        })
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// The length of every boarding pass.
+    pub fn pass_len(&self) -> usize {
+        bits_for(self.rows) + bits_for(self.cols)
+    }
+
+    pub fn seat(&self, row: usize, col: usize) -> Result<Seat, Box<dyn Error>> {
+        if row >= self.rows || col >= self.cols {
+            return Err(format!(
+                "Seat ({}, {}) outside {}x{} layout",
+                row, col, self.rows, self.cols
+            )
+            .into());
+        }
+        Ok(Seat {
+            row,
+            col,
+            id: row * self.cols + col,
+        })
+    }
+
+    pub fn encode(
+        &self,
+        row: usize,
+        col: usize,
+    ) -> Result<String, Box<dyn Error>> {
+        self.seat(row, col)?;
+        Ok(encode_bits(row, bits_for(self.rows), self.row_letters)
+            + &encode_bits(col, bits_for(self.cols), self.col_letters))
+    }
+
+    pub fn decode(&self, pass: &str) -> Result<Seat, Box<dyn Error>> {
+        let letters: Vec<char> = pass.chars().collect();
+        if letters.len() != self.pass_len() {
+            return Err(format!(
+                "Pass {:?} should have {} letters",
+                pass,
+                self.pass_len()
+            )
+            .into());
+        }
+        let (row, col) = letters.split_at(bits_for(self.rows));
+        let row = decode_bits(row, self.row_letters)?;
+        let col = decode_bits(col, self.col_letters)?;
+        self.seat(row, col)
+            .map_err(|e| format!("Pass {:?}: {}", pass, e).into())
+    }
+
+    /// Empty seats whose ids are between two taken seats.
+    pub fn candidates(&self, taken: &[Seat]) -> Vec<Seat> {
+        let ids: HashSet<usize> = taken.iter().map(|s| s.id).collect();
+        (1..(self.rows * self.cols).saturating_sub(1))
+            .filter(|id| {
+                !ids.contains(id)
+                    && ids.contains(&(id - 1))
+                    && ids.contains(&(id + 1))
+            })
+            .map(|id| self.seat(id / self.cols, id % self.cols).unwrap())
+            .collect()
+    }
+
+    /// A map with a line per row: `#` for taken seats, `O` for candidates
+    /// (see `candidates`) and `.` for other empty seats. Followed by counts
+    /// and the candidate ids.
+    pub fn seat_map(&self, taken: &[Seat]) -> String {
+        let ids: HashSet<usize> = taken.iter().map(|s| s.id).collect();
+        let candidates = self.candidates(taken);
+        let candidate_ids: HashSet<usize> =
+            candidates.iter().map(|s| s.id).collect();
+        let width = (self.rows - 1).to_string().len();
+        let mut s = String::new();
+        for row in 0..self.rows {
+            s.push_str(&format!("{:>width$} ", row, width = width));
+            for col in 0..self.cols {
+                let id = row * self.cols + col;
+                s.push(if ids.contains(&id) {
+                    '#'
+                } else if candidate_ids.contains(&id) {
+                    'O'
+                } else {
+                    '.'
+                });
+            }
+            s.push('\n');
+        }
+        let candidate_ids: Vec<_> =
+            candidates.iter().map(|s| s.id.to_string()).collect();
+        s.push_str(&format!(
+            "taken: {}, empty: {}, candidates: {}\n",
+            ids.len(),
+            self.rows * self.cols - ids.len(),
+            candidate_ids.join(" ")
+        ));
+        s
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_day5() {
+        let layout = SeatLayout::DAY5;
+        assert_eq!(layout.pass_len(), 10);
+        assert_eq!(
+            layout.decode("FBFBBFFRLR").unwrap(),
+            Seat {
+                row: 44,
+                col: 5,
+                id: 357
+            }
+        );
+        assert_eq!(layout.decode("BBFFBBFRLL").unwrap().id, 820);
+        assert_eq!(layout.encode(44, 5).unwrap(), "FBFBBFFRLR");
+        assert_eq!(layout.encode(102, 4).unwrap(), "BBFFBBFRLL");
+
+        assert!(layout.decode("FBFBBFFRL").is_err());
+        assert!(layout.decode("FBFBBFFRLRL").is_err());
+        assert!(layout.decode("FBFBBFFRLX").is_err());
+        assert!(layout.decode("FBFBBFRRLR").is_err());
+        assert!(layout.encode(128, 0).is_err());
+        assert!(layout.encode(0, 8).is_err());
+    }
+
+    #[test]
+    fn test_odd_layout() {
+        // 3 bits of rows, 2 of columns.
+        let layout = SeatLayout::new(5, 3, ('a', 'b'), ('0', '1')).unwrap();
+        assert_eq!(layout.pass_len(), 5);
+        for row in 0..5 {
+            for col in 0..3 {
+                let pass = layout.encode(row, col).unwrap();
+                assert_eq!(layout.decode(&pass).unwrap().id, row * 3 + col);
+            }
+        }
+        assert_eq!(layout.encode(4, 2).unwrap(), "baa10");
+        assert!(layout.decode("bab00").is_err());
+        assert!(layout.decode("aaa11").is_err());
+        assert!(layout.decode("aé000").is_err());
+
+        assert!(SeatLayout::new(0, 3, ('a', 'b'), ('0', '1')).is_err());
+        assert!(SeatLayout::new(5, 3, ('a', 'a'), ('0', '1')).is_err());
+        let single = SeatLayout::new(1, 2, ('a', 'b'), ('0', '1')).unwrap();
+        assert_eq!(single.encode(0, 1).unwrap(), "1");
+    }
+
+    #[test]
+    fn test_seat_map() {
+        let layout = SeatLayout::new(3, 4, ('F', 'B'), ('L', 'R')).unwrap();
+        let taken: Vec<_> = [0, 1, 2, 3, 4, 6, 7, 10]
+            .iter()
+            .map(|id| layout.seat(id / 4, id % 4).unwrap())
+            .collect();
+        assert_eq!(layout.candidates(&taken), vec![layout.seat(1, 1).unwrap()]);
+        assert_eq!(
+            layout.seat_map(&taken),
+            "\
+0 ####
+1 #O##
+2 ..#.
+taken: 8, empty: 4, candidates: 5
+"
+        );
+    }
+}
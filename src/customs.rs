@@ -0,0 +1,306 @@
+use crate::records::read_records;
+use std::error::Error;
+use std::fmt;
+use std::io::BufRead;
+use std::str::FromStr;
+
+/// The number of questions, `a` to `z`.
+pub const QUESTIONS: usize = 26;
+
+/// A set of questions, one bit each.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Answers(u32);
+
+impl Answers {
+    pub const NONE: Answers = Answers(0);
+    pub const ALL: Answers = Answers((1 << QUESTIONS) - 1);
+
+    pub fn contains(&self, question: char) -> bool {
+        question.is_ascii_lowercase()
+            && self.0 & 1 << (question as u8 - b'a') != 0
+    }
+
+    pub fn union(&self, other: &Answers) -> Answers {
+        Answers(self.0 | other.0)
+    }
+
+    pub fn intersection(&self, other: &Answers) -> Answers {
+        Answers(self.0 & other.0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// The questions, in order.
+    pub fn questions(&self) -> impl Iterator<Item = char> + '_ {
+        (b'a'..=b'z')
+            .map(char::from)
+            .filter(move |q| self.contains(*q))
+    }
+}
+
+/// One person's answers, as in `abc`.
+impl FromStr for Answers {
+    type Err = Box<dyn Error>;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.chars().try_fold(Answers::NONE, |acc, c| {
+            if c.is_ascii_lowercase() {
+                Ok(Answers(acc.0 | 1 << (c as u8 - b'a')))
+            } else {
+                Err(format!("Bad question {:?}", c).into())
+            }
+        })
+    }
+}
+
+impl fmt::Display for Answers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.questions().try_for_each(|q| write!(f, "{}", q))
+    }
+}
+
+/// A per-group question selection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Query {
+    /// Answered by anyone.
+    Union,
+    /// Answered by everyone.
+    Intersection,
+    /// Answered by at least this many people.
+    AtLeast(usize),
+    /// Answered by exactly this many people.
+    Exactly(usize),
+}
+
+/// `any`, `all`, `at-least K` or `exactly K`.
+impl FromStr for Query {
+    type Err = Box<dyn Error>;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words: Vec<&str> = s.split_ascii_whitespace().collect();
+        match words.as_slice() {
+            ["any"] => Ok(Query::Union),
+            ["all"] => Ok(Query::Intersection),
+            ["at-least", k] => Ok(Query::AtLeast(k.parse()?)),
+            ["exactly", k] => Ok(Query::Exactly(k.parse()?)),
+            _ => Err(format!("Bad query {:?}", s).into()),
+        }
+    }
+}
+
+/// The answers of everyone in a group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Group {
+    pub people: Vec<Answers>,
+}
+
+impl Group {
+    /// How many people answered each question.
+    pub fn counts(&self) -> [usize; QUESTIONS] {
+        let mut counts = [0; QUESTIONS];
+        for person in &self.people {
+            for (i, count) in counts.iter_mut().enumerate() {
+                *count += (person.0 >> i & 1) as usize;
+            }
+        }
+        counts
+    }
+
+    // Questions whose count satisfies `pred`.
+    fn select<F: Fn(usize) -> bool>(&self, pred: F) -> Answers {
+        let bits = self
+            .counts()
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| pred(**count))
+            .fold(0, |acc, (i, _)| acc | 1 << i);
+        Answers(bits)
+    }
+
+    pub fn eval(&self, query: Query) -> Answers {
+        match query {
+            Query::Union => {
+                self.people.iter().fold(Answers::NONE, |a, b| a.union(b))
+            }
+            Query::Intersection => self
+                .people
+                .iter()
+                .fold(Answers::ALL, |a, b| a.intersection(b))
+                .intersection(&self.eval(Query::Union)),
+            Query::AtLeast(k) => self.select(|count| count > 0 && count >= k),
+            Query::Exactly(k) => self.select(|count| count > 0 && count == k),
+        }
+    }
+}
+
+impl FromStr for Group {
+    type Err = Box<dyn Error>;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let people = s.lines().map(str::parse).collect::<Result<_, _>>()?;
+        Ok(Group { people })
+    }
+}
+
+/// Reads blank-line-separated groups.
+pub fn read_groups<R: BufRead>(
+    reader: R,
+) -> impl Iterator<Item = Result<Group, Box<dyn Error>>> {
+    read_records(reader).map(|record| {
+        let record = record?;
+        record
+            .text
+            .parse()
+            .map_err(|e| format!("Line {}: {}", record.line, e).into())
+    })
+}
+
+/// The sum over all groups of the number of questions selected by `query`.
+pub fn sum(groups: &[Group], query: Query) -> usize {
+    groups.iter().map(|g| g.eval(query).len()).sum()
+}
+
+/// Like `sum`, reading one group at a time.
+pub fn read_sum<R: BufRead>(
+    reader: R,
+    query: Query,
+) -> Result<usize, Box<dyn Error>> {
+    read_groups(reader)
+        .try_fold(0, |acc, group| Ok(acc + group?.eval(query).len()))
+}
+
+// Adds how many people in `group` answered each question to `total`.
+fn add_counts(
+    mut total: [usize; QUESTIONS],
+    group: &Group,
+) -> [usize; QUESTIONS] {
+    for (total, count) in total.iter_mut().zip(group.counts().iter()) {
+        *total += count;
+    }
+    total
+}
+
+/// How many people answered each question, across all groups.
+pub fn histogram(groups: &[Group]) -> [usize; QUESTIONS] {
+    groups.iter().fold([0; QUESTIONS], add_counts)
+}
+
+/// Like `histogram`, reading one group at a time.
+pub fn read_histogram<R: BufRead>(
+    reader: R,
+) -> Result<[usize; QUESTIONS], Box<dyn Error>> {
+    read_groups(reader).try_fold([0; QUESTIONS], |total, group| {
+        Ok(add_counts(total, &group?))
+    })
+}
+
+/// A line per question with its count and a bar scaled to at most `width`.
+pub fn histogram_report(
+    histogram: &[usize; QUESTIONS],
+    width: usize,
+) -> String {
+    let max = histogram.iter().copied().max().unwrap_or(0).max(1);
+    let count_width = max.to_string().len();
+    let mut report = String::new();
+    for (q, count) in (b'a'..=b'z').zip(histogram.iter()) {
+        report.push_str(&format!(
+            "{} {:>w$}",
+            q as char,
+            count,
+            w = count_width
+        ));
+        let bar = count * width / max;
+        if bar > 0 {
+            report.push(' ');
+            report.push_str(&"#".repeat(bar));
+        }
+        report.push('\n');
+    }
+    report
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    static INPUT: &str = "\
+abc
+
+a
+b
+c
+
+ab
+ac
+
+a
+a
+a
+a
+
+b";
+
+    #[test]
+    fn test_answers() {
+        let a: Answers = "cab".parse().unwrap();
+        assert_eq!(a.len(), 3);
+        assert!(a.contains('b') && !a.contains('d') && !a.contains('B'));
+        assert_eq!(a.to_string(), "abc");
+        assert_eq!(a.intersection(&"bcd".parse().unwrap()).to_string(), "bc");
+        assert_eq!(a.union(&"z".parse().unwrap()).to_string(), "abcz");
+        assert!("aB".parse::<Answers>().is_err());
+    }
+
+    #[test]
+    fn test_queries() {
+        let groups: Vec<Group> = read_groups(Cursor::new(INPUT))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(groups.len(), 5);
+        assert_eq!(sum(&groups, Query::Union), 11);
+        assert_eq!(sum(&groups, Query::Intersection), 6);
+        assert_eq!(sum(&groups, Query::AtLeast(2)), 2);
+        assert_eq!(sum(&groups, Query::Exactly(1)), 9);
+        assert_eq!(sum(&groups, "at-least 1".parse().unwrap()), 11);
+        assert_eq!(
+            read_sum(Cursor::new(INPUT), Query::Intersection).unwrap(),
+            6
+        );
+
+        let group: Group = "ab\nac\nabd".parse().unwrap();
+        assert_eq!(group.eval(Query::Intersection).to_string(), "a");
+        assert_eq!(group.eval(Query::Exactly(2)).to_string(), "b");
+        assert_eq!(group.eval(Query::Exactly(1)).to_string(), "cd");
+        assert_eq!(group.eval(Query::AtLeast(0)).to_string(), "abcd");
+        assert_eq!(
+            Group { people: vec![] }.eval(Query::Intersection),
+            Answers::NONE
+        );
+
+        assert!("some".parse::<Query>().is_err());
+        assert!("exactly".parse::<Query>().is_err());
+    }
+
+    #[test]
+    fn test_histogram() {
+        let groups: Vec<Group> = read_groups(Cursor::new(INPUT))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let histogram = histogram(&groups);
+        assert_eq!(histogram[..4], [8, 4, 3, 0]);
+        assert_eq!(read_histogram(Cursor::new(INPUT)).unwrap(), histogram);
+        assert!(read_histogram(Cursor::new("a\n\nA")).is_err());
+        assert!(histogram_report(&histogram, 4).starts_with(
+            "\
+a 8 ####
+b 4 ##
+c 3 #
+d 0
+"
+        ));
+    }
+}
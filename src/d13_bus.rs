@@ -36,6 +36,7 @@ pub fn part2(input: &str) -> u64 {
         // Move time forward by delta until we find a time that has the right offset for this
         // train.
         loop {
+            #[allow(clippy::manual_is_multiple_of)]
             if (t + *offset) % dt == 0 {
                 // This time produces the right offset for this train.
                 break;
@@ -74,6 +75,7 @@ pub fn part2_naive(input: &str) -> u64 {
     let mut t: u64 = constraints[0].1 - (constraints[0].0 % constraints[0].1);
     let mut i = 0u64;
     loop {
+        #[allow(clippy::manual_is_multiple_of)]
         if constraints[1..constraints.len()]
             .iter()
             .all(|(offset, dt)| (t + offset) % dt == 0)
@@ -82,6 +84,7 @@ pub fn part2_naive(input: &str) -> u64 {
         }
         t += delta;
         i += 1;
+        #[allow(clippy::manual_is_multiple_of)]
         if i % 1000000000u64 == 0 {
             println!("{}", t);
         }
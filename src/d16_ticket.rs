@@ -1,3 +1,4 @@
+use crate::records::records;
 use lazy_static::lazy_static;
 use regex::Regex;
 use std::collections::{HashMap, HashSet};
@@ -40,7 +41,7 @@ impl From<&str> for Rule {
 }
 
 pub fn part1(input: &str) -> u64 {
-    let mut sections = input.split("\n\n");
+    let mut sections = records(input).map(|r| r.text);
     let rules: Vec<Rule> =
         sections.next().unwrap().lines().map(Rule::from).collect();
     sections.next(); // Skip my ticket
@@ -59,7 +60,7 @@ pub fn part1(input: &str) -> u64 {
 }
 
 pub fn part2(input: &str) -> u64 {
-    let mut sections = input.split("\n\n");
+    let mut sections = records(input).map(|r| r.text);
     let rules: Vec<Rule> =
         sections.next().unwrap().lines().map(Rule::from).collect();
     let mine: Vec<u64> = sections
@@ -1,143 +1,468 @@
-use lazy_static::lazy_static;
-use std::collections::HashSet;
-
-type Pos = (i32, i32, i32, i32);
-type State = HashSet<Pos>;
-
-lazy_static! {
-    static ref DELTAS3: Vec<Pos> = {
-        let mut d = Vec::new();
-        for dx in -1..=1 {
-            for dy in -1..=1 {
-                for dz in -1..=1 {
-                    if dx == 0 && dy == 0 && dz == 0 {
-                        continue;
-                    }
-                    d.push((dx, dy, dz, 0));
-                }
-            }
+use crate::automaton::{Automaton, Neighborhood, Rule, Sparse, Square};
+use ndarray::{ArrayD, ArrayViewD, Axis, IxDyn, Slice};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+
+// Names of the dimensions, as used in the puzzle text.
+fn dim_name(d: usize) -> String {
+    match d {
+        0 => "x".into(),
+        1 => "y".into(),
+        2 => "z".into(),
+        3 => "w".into(),
+        _ => format!("d{}", d),
+    }
+}
+
+/// Renders each x/y slice of the bounding box of `live`, labelled by its
+/// other coordinates, in the puzzle's format. The last dimension varies
+/// slowest.
+pub fn state_to_string<const N: usize>(live: &[[i32; N]]) -> String {
+    if live.is_empty() {
+        return String::new();
+    }
+    let min: Vec<i32> = (0..N)
+        .map(|d| live.iter().map(|c| c[d]).min().unwrap())
+        .collect();
+    let max: Vec<i32> = (0..N)
+        .map(|d| live.iter().map(|c| c[d]).max().unwrap())
+        .collect();
+    let live: HashSet<_> = live.iter().collect();
+
+    // Every combination of extra coordinates in the bounding box, with the
+    // first extra dimension varying fastest.
+    let mut slices = vec![vec![]];
+    for d in (2..N).rev() {
+        slices = slices
+            .into_iter()
+            .flat_map(|slice: Vec<i32>| {
+                (min[d]..=max[d]).map(move |i| {
+                    let mut slice = slice.clone();
+                    slice.insert(0, i);
+                    slice
+                })
+            })
+            .collect();
+    }
+    let mut res = Vec::new();
+    for extra in slices {
+        let mut s = String::new();
+        if N > 2 {
+            let labels: Vec<_> = extra
+                .iter()
+                .enumerate()
+                .map(|(i, v)| format!("{}={}", dim_name(i + 2), v))
+                .collect();
+            s.push_str(&labels.join(", "));
+            s.push('\n');
         }
-        d
-    };
-    static ref DELTAS4: Vec<Pos> = {
-        let mut d = Vec::new();
-        for dx in -1..=1 {
-            for dy in -1..=1 {
-                for dz in -1..=1 {
-                    for dw in -1..=1 {
-                        if dx == 0 && dy == 0 && dz == 0 && dw == 0 {
-                            continue;
-                        }
-                        d.push((dx, dy, dz, dw));
-                    }
-                }
+        let mut cell = [0; N];
+        cell[2..].copy_from_slice(&extra);
+        for y in min[1]..=max[1] {
+            for x in min[0]..=max[0] {
+                cell[0] = x;
+                cell[1] = y;
+                s.push(if live.contains(&cell) { '#' } else { '.' });
             }
+            s.push('\n');
         }
-        d
-    };
-}
-
-/*
-fn state2string(state: &State) -> String {
-    use std::cmp::{min,max};
-    let mut minx=i32::MAX;
-    let mut maxx=i32::MIN;
-    let mut miny=i32::MAX;
-    let mut maxy=i32::MIN;
-    let mut minz=i32::MAX;
-    let mut maxz=i32::MIN;
-    for (x,y,z) in state {
-        minx = min(minx,*x);
-        miny = min(miny,*y);
-        minz = min(minz,*z);
-        maxx = max(maxx,*x);
-        maxy = max(maxy,*y);
-        maxz = max(maxz,*z);
-    }
-    let mut res = String::new();
-    for z in minz..=maxz {
-        res.push_str(&format!("z={}\n", z));
-        for y in miny..=maxy {
-            for x in minx..=maxx {
-                res.push( if state.contains(&(x,y,z)) {
-                    '#'
-                } else {
-                    '.'
-                });
+        res.push(s);
+    }
+    res.join("\n")
+}
+
+// Dimensions beyond the first two start at 0.
+fn parse<const N: usize>(input: &str) -> Vec<[i32; N]> {
+    let mut res = Vec::new();
+    for (y, line) in input.lines().enumerate() {
+        for (x, c) in line.chars().enumerate() {
+            if c == '#' {
+                let mut pos = [0; N];
+                pos[0] = x as i32;
+                pos[1] = y as i32;
+                res.push(pos);
             }
-            res.push('\n');
         }
     }
     res
 }
-*/
 
-fn candidates(state: &State, deltas: &[Pos]) -> HashSet<Pos> {
-    let mut res = HashSet::<Pos>::new();
-    for pos in state {
-        res.insert(*pos);
-        for (dx, dy, dz, dw) in deltas.iter() {
-            res.insert((pos.0 + dx, pos.1 + dy, pos.2 + dz, pos.3 + dw));
+/// Pocket dimension cubes, exploiting their symmetry. Every dimension after
+/// the first two starts out at 0, so the state stays symmetric under negating
+/// any of those coordinates, or swapping any two of them. We only store the
+/// canonical cell of each set of cells related that way: the one whose extra
+/// coordinates are non-negative and sorted.
+pub struct SymmetricCubes<const N: usize> {
+    deltas: Vec<[i32; N]>,
+    rule: Rule,
+    // Canonical live cells.
+    live: HashSet<[i32; N]>,
+}
+
+fn canonical<const N: usize>(mut cell: [i32; N]) -> [i32; N] {
+    for c in cell.iter_mut().skip(2) {
+        *c = c.abs();
+    }
+    cell[2.min(N)..].sort_unstable();
+    cell
+}
+
+// Number of cells that `canonical` maps to the same cell as `cell`.
+fn orbit_size<const N: usize>(cell: &[i32; N]) -> usize {
+    let extra = &cell[2.min(N)..];
+    let factorial = |n: usize| (1..=n).product::<usize>();
+    let signs = 1 << extra.iter().filter(|c| **c != 0).count();
+    let mut permutations = factorial(extra.len());
+    let mut counts = HashMap::<i32, usize>::new();
+    for c in extra {
+        *counts.entry(c.abs()).or_insert(0) += 1;
+    }
+    for count in counts.values() {
+        permutations /= factorial(*count);
+    }
+    signs * permutations
+}
+
+impl<const N: usize> SymmetricCubes<N> {
+    pub fn new(input: &str) -> SymmetricCubes<N> {
+        SymmetricCubes {
+            deltas: Square::<N>::new(Neighborhood::Moore).deltas().to_vec(),
+            rule: "B3/S23".parse().unwrap(),
+            live: parse::<N>(input).into_iter().collect(),
         }
     }
-    res
 }
 
-fn next(state: &State, deltas: &[Pos]) -> State {
-    let mut next_state = State::new();
-    for (x, y, z, w) in candidates(state, deltas) {
-        let neighbor_count: u8 = deltas
-            .iter()
-            .map(|(dx, dy, dz, dw)| {
-                if state.contains(&(x + dx, y + dy, z + dz, w + dw)) {
-                    1
+impl<const N: usize> Automaton for SymmetricCubes<N> {
+    type Cell = [i32; N];
+
+    fn step(&mut self) -> bool {
+        // For canonical cells `c` and `m`, the live neighbors of `m` that
+        // canonicalize to `c` number `orbit_size(c) / orbit_size(m)` times
+        // the neighbors of `c` that canonicalize to `m`. (Both count the
+        // adjacent pairs between the two orbits.) Accumulate the numerator
+        // here and divide at the end.
+        let mut weighted_counts = HashMap::<[i32; N], usize>::new();
+        for cell in &self.live {
+            let weight = orbit_size(cell);
+            for delta in &self.deltas {
+                let mut neighbor = *cell;
+                for d in 0..N {
+                    neighbor[d] += delta[d];
+                }
+                *weighted_counts.entry(canonical(neighbor)).or_insert(0) +=
+                    weight;
+            }
+        }
+
+        let mut next = HashSet::new();
+        for cell in &self.live {
+            if !weighted_counts.contains_key(cell) && self.rule.next(true, 0) {
+                next.insert(*cell);
+            }
+        }
+        for (cell, weighted_count) in &weighted_counts {
+            let orbit = orbit_size(cell);
+            debug_assert_eq!(weighted_count % orbit, 0);
+            if self
+                .rule
+                .next(self.live.contains(cell), weighted_count / orbit)
+            {
+                next.insert(*cell);
+            }
+        }
+
+        let changed = next != self.live;
+        self.live = next;
+        changed
+    }
+
+    fn population(&self) -> usize {
+        self.live.iter().map(orbit_size).sum()
+    }
+
+    fn is_alive(&self, cell: &[i32; N]) -> bool {
+        self.live.contains(&canonical(*cell))
+    }
+
+    fn live_cells(&self) -> Vec<[i32; N]> {
+        // Expand each canonical cell by flipping signs and permuting the
+        // extra coordinates.
+        let mut res = HashSet::new();
+        for cell in &self.live {
+            let mut orbit = vec![*cell];
+            for d in 2..N {
+                orbit = orbit
+                    .into_iter()
+                    .flat_map(|c| {
+                        let mut negated = c;
+                        negated[d] = -negated[d];
+                        vec![c, negated]
+                    })
+                    .collect();
+            }
+            for d in 2..N {
+                for e in d + 1..N {
+                    orbit = orbit
+                        .into_iter()
+                        .flat_map(|c| {
+                            let mut swapped = c;
+                            swapped.swap(d, e);
+                            vec![c, swapped]
+                        })
+                        .collect();
+                }
+            }
+            res.extend(orbit);
+        }
+        res.into_iter().collect()
+    }
+}
+
+/// Pocket dimension cubes, stored as an array covering their bounding box.
+/// The box grows by one in each direction every cycle.
+pub struct DenseCubes<const N: usize> {
+    deltas: Vec<[i32; N]>,
+    rule: Rule,
+    // Coordinates of `cells[[0, 0, ...]]`.
+    origin: [i32; N],
+    cells: ArrayD<u32>,
+}
+
+// The part of `a` starting at `start` with the given `shape`.
+fn window<'a>(
+    mut a: ArrayViewD<'a, u32>,
+    start: &[usize],
+    shape: &[usize],
+) -> ArrayViewD<'a, u32> {
+    for (axis, (start, len)) in start.iter().zip(shape.iter()).enumerate() {
+        a.slice_axis_inplace(Axis(axis), Slice::from(*start..*start + *len));
+    }
+    a
+}
+
+impl<const N: usize> DenseCubes<N> {
+    pub fn new(input: &str) -> DenseCubes<N> {
+        let live = parse::<N>(input);
+        let mut origin = [0; N];
+        let mut shape = [0; N];
+        if !live.is_empty() {
+            for d in 0..N {
+                let min = live.iter().map(|c| c[d]).min().unwrap();
+                let max = live.iter().map(|c| c[d]).max().unwrap();
+                origin[d] = min;
+                shape[d] = (max - min + 1) as usize;
+            }
+        }
+        let mut cells = ArrayD::zeros(IxDyn(&shape));
+        for cell in live {
+            let idx: Vec<usize> =
+                (0..N).map(|d| (cell[d] - origin[d]) as usize).collect();
+            cells[IxDyn(&idx)] = 1;
+        }
+        DenseCubes {
+            deltas: Square::<N>::new(Neighborhood::Moore).deltas().to_vec(),
+            rule: "B3/S23".parse().unwrap(),
+            origin,
+            cells,
+        }
+    }
+
+    fn index(&self, cell: &[i32; N]) -> Option<Vec<usize>> {
+        (0..N)
+            .map(|d| {
+                let i = cell[d] - self.origin[d];
+                if i >= 0 && (i as usize) < self.cells.shape()[d] {
+                    Some(i as usize)
                 } else {
-                    0
+                    None
                 }
             })
-            .sum();
-        let active = state.contains(&(x, y, z, w));
-        if neighbor_count == 3 || active && neighbor_count == 2 {
-            let pos = (x, y, z, w);
-            // println!("  Inserting {:?}", pos);
-            next_state.insert(pos);
-        }
+            .collect()
     }
-    next_state
 }
 
-fn parse(input: &str) -> State {
-    let mut res = State::new();
-    for (y, line) in input.lines().enumerate() {
-        for (x, c) in line.chars().enumerate() {
-            if c == '#' {
-                res.insert((x as i32, y as i32, 0, 0));
+impl<const N: usize> Automaton for DenseCubes<N> {
+    type Cell = [i32; N];
+
+    fn step(&mut self) -> bool {
+        let shape = self.cells.shape().to_vec();
+        let grown: Vec<usize> = shape.iter().map(|s| s + 2).collect();
+
+        // Pad by 2, so that every cell of the grown array has all of its
+        // neighbors in `padded`.
+        let padded_shape: Vec<usize> = shape.iter().map(|s| s + 4).collect();
+        let mut padded = ArrayD::<u32>::zeros(IxDyn(&padded_shape));
+        {
+            let mut interior = padded.view_mut();
+            for (axis, len) in shape.iter().enumerate() {
+                interior
+                    .slice_axis_inplace(Axis(axis), Slice::from(2..2 + len));
             }
+            interior.assign(&self.cells);
+        }
+
+        // Sum the array shifted by each delta.
+        let mut counts = ArrayD::<u32>::zeros(IxDyn(&grown));
+        for delta in &self.deltas {
+            let start: Vec<usize> =
+                delta.iter().map(|d| (1 + d) as usize).collect();
+            counts += &window(padded.view(), &start, &grown);
+        }
+
+        let rule = &self.rule;
+        counts.zip_mut_with(&window(padded.view(), &[1; N], &grown), |n, a| {
+            *n = rule.next(*a == 1, *n as usize) as u32
+        });
+        let changed = counts != window(padded.view(), &[1; N], &grown);
+
+        for o in self.origin.iter_mut() {
+            *o -= 1;
+        }
+        self.cells = counts;
+        changed
+    }
+
+    fn population(&self) -> usize {
+        self.cells.iter().filter(|c| **c == 1).count()
+    }
+
+    fn is_alive(&self, cell: &[i32; N]) -> bool {
+        match self.index(cell) {
+            Some(idx) => self.cells[IxDyn(&idx)] == 1,
+            None => false,
+        }
+    }
+
+    fn live_cells(&self) -> Vec<[i32; N]> {
+        self.cells
+            .indexed_iter()
+            .filter(|(_, c)| **c == 1)
+            .map(|(idx, _)| {
+                let mut cell = self.origin;
+                for (d, c) in cell.iter_mut().enumerate() {
+                    *c += idx[d] as i32;
+                }
+                cell
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CubeBackend {
+    /// The generic automaton's sparse backend.
+    Sparse,
+    /// `SymmetricCubes`.
+    Symmetric,
+    /// `DenseCubes`.
+    Dense,
+}
+
+pub fn new_cubes<const N: usize>(
+    input: &str,
+    backend: CubeBackend,
+) -> Box<dyn Automaton<Cell = [i32; N]>> {
+    match backend {
+        CubeBackend::Sparse => Box::new(
+            Sparse::new(
+                Square::<N>::new(Neighborhood::Moore),
+                "B3/S23".parse().unwrap(),
+                parse::<N>(input),
+            )
+            .unwrap(),
+        ),
+        CubeBackend::Symmetric => Box::new(SymmetricCubes::<N>::new(input)),
+        CubeBackend::Dense => Box::new(DenseCubes::<N>::new(input)),
+    }
+}
+
+/// Active cubes after `cycles` in `N` dimensions.
+pub fn simulate_n<const N: usize>(
+    input: &str,
+    cycles: usize,
+    backend: CubeBackend,
+) -> usize {
+    let mut automaton = new_cubes::<N>(input, backend);
+    automaton.run(cycles);
+    automaton.population()
+}
+
+/// Active cubes after `cycles` in `dims` dimensions, for up to 8 dimensions.
+pub fn simulate(
+    input: &str,
+    dims: usize,
+    cycles: usize,
+    backend: CubeBackend,
+) -> Result<usize, Box<dyn Error>> {
+    Ok(match dims {
+        2 => simulate_n::<2>(input, cycles, backend),
+        3 => simulate_n::<3>(input, cycles, backend),
+        4 => simulate_n::<4>(input, cycles, backend),
+        5 => simulate_n::<5>(input, cycles, backend),
+        6 => simulate_n::<6>(input, cycles, backend),
+        7 => simulate_n::<7>(input, cycles, backend),
+        8 => simulate_n::<8>(input, cycles, backend),
+        _ => return Err(format!("Unsupported dimensions: {}", dims).into()),
+    })
+}
+
+/// Sorted active cubes before each cycle and after the last.
+pub fn history<const N: usize>(
+    input: &str,
+    cycles: usize,
+    backend: CubeBackend,
+) -> Vec<Vec<[i32; N]>> {
+    let mut automaton = new_cubes::<N>(input, backend);
+    let mut res = Vec::new();
+    for cycle in 0..=cycles {
+        if cycle > 0 {
+            automaton.step();
         }
+        let mut live = automaton.live_cells();
+        live.sort_unstable();
+        res.push(live);
     }
     res
 }
 
-pub fn part1(input: &str) -> usize {
-    let mut state = parse(input);
-    //println!("Initial:\n{}", state2string(&state));
-    for _i in 0..6 {
-        //println!("Round {}", i);
-        state = next(&state, &DELTAS3);
-        //println!("{}", state2string(&state));
+/// Every slice after every cycle, in the puzzle's format.
+pub fn history_to_string<const N: usize>(history: &[Vec<[i32; N]>]) -> String {
+    let mut res = Vec::new();
+    for (cycle, live) in history.iter().enumerate() {
+        let heading = match cycle {
+            0 => "Before any cycles:".to_string(),
+            1 => "After 1 cycle:".to_string(),
+            _ => format!("After {} cycles:", cycle),
+        };
+        res.push(format!("{}\n\n{}", heading, state_to_string(live)));
     }
-    state.len()
+    res.join("\n\n")
+}
+
+/// The history as JSON: the dimension names, and each cycle's active cubes
+/// as coordinate arrays.
+pub fn history_to_json<const N: usize>(history: &[Vec<[i32; N]>]) -> String {
+    let dims: Vec<_> = (0..N).map(dim_name).collect();
+    let cycles: Vec<_> = history
+        .iter()
+        .enumerate()
+        .map(|(cycle, live)| {
+            serde_json::json!({
+                "cycle": cycle,
+                "active": live.iter().map(|c| c.to_vec()).collect::<Vec<_>>(),
+            })
+        })
+        .collect();
+    serde_json::json!({ "dimensions": dims, "cycles": cycles }).to_string()
+}
+
+pub fn part1(input: &str) -> usize {
+    simulate_n::<3>(input, 6, CubeBackend::Symmetric)
 }
 
 pub fn part2(input: &str) -> usize {
-    let mut state = parse(input);
-    //println!("Initial:\n{}", state2string(&state));
-    for _i in 0..6 {
-        //println!("Round {}", i);
-        state = next(&state, &DELTAS4);
-        //println!("{}", state2string(&state));
-    }
-    state.len()
+    simulate_n::<4>(input, 6, CubeBackend::Symmetric)
 }
 
 #[cfg(test)]
@@ -150,3 +475,152 @@ fn test_part1() {
     assert_eq!(part1(input), 112);
     assert_eq!(part2(input), 848);
 }
+
+#[cfg(test)]
+#[test]
+fn test_symmetry() {
+    assert_eq!(canonical([1, 2, -3, 0, 2]), [1, 2, 0, 2, 3]);
+    // Signs of the two 2s, and 3 distinct arrangements of 0, 2, and 2.
+    assert_eq!(orbit_size(&[1, 2, 0, 2, 2]), 4 * 3);
+    assert_eq!(orbit_size(&[1, 2, 0, 0, 0]), 1);
+    assert_eq!(orbit_size(&[1, 2, 1, 2, 3]), 8 * 6);
+    assert_eq!(orbit_size(&[1, 2]), 1);
+
+    let input = "\
+.#.
+..#
+###";
+    assert_eq!(simulate_n::<3>(input, 6, CubeBackend::Sparse), 112);
+    assert_eq!(simulate_n::<4>(input, 6, CubeBackend::Sparse), 848);
+    for cycles in 0..4 {
+        for dims in &[2, 5] {
+            assert_eq!(
+                simulate(input, *dims, cycles, CubeBackend::Symmetric).unwrap(),
+                simulate(input, *dims, cycles, CubeBackend::Sparse).unwrap(),
+            );
+        }
+    }
+    assert!(simulate(input, 9, 6, CubeBackend::Symmetric).is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn test_backends_agree() {
+    let input = "\
+.#.
+..#
+###";
+    let mut sparse = new_cubes::<4>(input, CubeBackend::Sparse);
+    for backend in &[CubeBackend::Symmetric, CubeBackend::Dense] {
+        let mut cubes = new_cubes::<4>(input, *backend);
+        for _ in 0..3 {
+            assert_eq!(cubes.step(), sparse.step());
+            let mut expected = sparse.live_cells();
+            expected.sort_unstable();
+            let mut actual = cubes.live_cells();
+            actual.sort_unstable();
+            assert_eq!(actual, expected);
+            assert!(cubes.is_alive(&expected[0]));
+            assert!(!cubes.is_alive(&[100, 0, 0, 0]));
+        }
+        sparse = new_cubes::<4>(input, CubeBackend::Sparse);
+    }
+    assert_eq!(simulate_n::<3>(input, 6, CubeBackend::Dense), 112);
+    assert_eq!(simulate_n::<4>(input, 6, CubeBackend::Dense), 848);
+    assert_eq!(simulate_n::<3>("", 6, CubeBackend::Dense), 0);
+}
+
+#[cfg(test)]
+#[test]
+fn test_history() {
+    let input = "\
+.#.
+..#
+###";
+    let hist = history::<3>(input, 2, CubeBackend::Dense);
+    assert_eq!(hist, history::<3>(input, 2, CubeBackend::Symmetric));
+    assert_eq!(
+        history_to_string(&hist),
+        "\
+Before any cycles:
+
+z=0
+.#.
+..#
+###
+
+
+After 1 cycle:
+
+z=-1
+#..
+..#
+.#.
+
+z=0
+#.#
+.##
+.#.
+
+z=1
+#..
+..#
+.#.
+
+
+After 2 cycles:
+
+z=-2
+.....
+.....
+..#..
+.....
+.....
+
+z=-1
+..#..
+.#..#
+....#
+.#...
+.....
+
+z=0
+##...
+##...
+#....
+....#
+.###.
+
+z=1
+..#..
+.#..#
+....#
+.#...
+.....
+
+z=2
+.....
+.....
+..#..
+.....
+.....
+"
+    );
+
+    let hist = history::<4>(input, 1, CubeBackend::Sparse);
+    let printed = history_to_string(&hist);
+    assert!(printed.contains("\nz=-1, w=-1\n#..\n..#\n.#.\n"));
+    assert!(printed.contains("\nz=1, w=1\n#..\n..#\n.#.\n"));
+
+    let json: serde_json::Value =
+        serde_json::from_str(&history_to_json(&hist)).unwrap();
+    assert_eq!(json["dimensions"], serde_json::json!(["x", "y", "z", "w"]));
+    assert_eq!(
+        json["cycles"][0]["active"][0],
+        serde_json::json!([0, 2, 0, 0])
+    );
+    assert_eq!(
+        json["cycles"][1]["active"].as_array().unwrap().len(),
+        hist[1].len()
+    );
+}
@@ -33,7 +33,7 @@ mod v1 {
                     s = &s[1..];
                 }
                 _ => {
-                    let end = match s.find(|c: char| !c.is_digit(10)) {
+                    let end = match s.find(|c: char| !c.is_ascii_digit()) {
                         Some(i) => i,
                         None => s.len(),
                     };
@@ -119,7 +119,7 @@ mod v1 {
                 Plus | Times => {
                     while !ops_stack.is_empty()
                         && priority(&ops_stack[ops_stack.len() - 1])
-                            >= priority(&token)
+                            >= priority(token)
                     {
                         res.push(ops_stack.pop().unwrap());
                     }
@@ -255,7 +255,7 @@ mod v0 {
     }
 
     fn take_num(s: &str) -> (u64, &str) {
-        let end = match s.find(|c: char| !c.is_digit(10)) {
+        let end = match s.find(|c: char| !c.is_ascii_digit()) {
             Some(i) => i,
             None => s.len(),
         };
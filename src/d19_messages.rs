@@ -1,3 +1,4 @@
+use crate::records::records;
 use regex::Regex;
 use std::collections::HashMap;
 
@@ -53,7 +54,7 @@ impl RuleSet {
         let tail = &rule_list[1..];
         let res = match self.rules.get(&head).unwrap() {
             Char(c) => msg.starts_with(*c) && self.matches(tail, &msg[1..]),
-            List(l) => self.matches(&prepend(l, &tail), msg),
+            List(l) => self.matches(&prepend(l, tail), msg),
             Disj(l1, l2) => {
                 self.matches(&prepend(l1, tail), msg)
                     || self.matches(&prepend(l2, tail), msg)
@@ -79,7 +80,7 @@ impl RuleSet {
 }
 
 pub fn part1_regex(input: &str) -> usize {
-    let mut it = input.split("\n\n");
+    let mut it = records(input).map(|r| r.text);
     let rules = it.next().unwrap();
     let messages = it.next().unwrap();
     let rule_set = parse_rules(rules);
@@ -91,7 +92,7 @@ pub fn part1_regex(input: &str) -> usize {
 }
 
 pub fn part1(input: &str) -> usize {
-    let mut it = input.split("\n\n");
+    let mut it = records(input).map(|r| r.text);
     let rules = it.next().unwrap();
     let messages = it.next().unwrap();
     let rule_set = parse_rules(rules);
@@ -102,7 +103,7 @@ pub fn part1(input: &str) -> usize {
 }
 
 pub fn part2(input: &str) -> usize {
-    let mut it = input.split("\n\n");
+    let mut it = records(input).map(|r| r.text);
     let rules = it.next().unwrap();
     let messages = it.next().unwrap();
     let mut rule_set = parse_rules(rules);
@@ -1,12 +1,19 @@
+use crate::raster::{self, Rgb};
+use crate::records::records;
 use ndarray::{s, Array, Array2};
+use rand::seq::SliceRandom;
+use rand::Rng;
 use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::io::Write;
 
-type Orientation = (/* flipped: */ bool, /* rotations: */ u8);
-type TileId = usize;
-type Pos = (i16, i16);
+pub type Orientation = (/* flipped: */ bool, /* rotations: */ u8);
+pub type TileId = usize;
+/// (row, col) of a tile within the assembled grid.
+pub type Pos = (i16, i16);
 type Puzzle = HashMap<Pos, (TileId, Tile)>;
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, Hash)]
 struct TileSide(Vec<i8>);
 
 impl TileSide {
@@ -28,6 +35,11 @@ static ORIENTATIONS: [Orientation; 8] = [
     (true, 3),
 ];
 
+// Rotating a non-square tile by 90 degrees changes its shape, so it could no
+// longer sit in the same grid as its (unrotated) neighbors.
+static RECT_ORIENTATIONS: [Orientation; 4] =
+    [(false, 0), (false, 2), (true, 0), (true, 2)];
+
 enum Side {
     Top,
     Bottom,
@@ -35,81 +47,128 @@ enum Side {
     Right,
 }
 
+/// Flips (top to bottom) and then rotates (clockwise) `a`.
+fn transform<T: Clone>(a: &Array2<T>, ori: &Orientation) -> Array2<T> {
+    let (height, width) = a.dim();
+
+    let mut res = if ori.0 {
+        Array2::from_shape_fn((height, width), |(y, x)| {
+            a[(height - y - 1, x)].clone()
+        })
+    } else {
+        a.clone()
+    };
+
+    // rotate `ori.1` times. Each rotation swaps height and width.
+    for _i in 0..ori.1 {
+        let (height, width) = res.dim();
+        res = Array2::from_shape_fn((width, height), |(y, x)| {
+            res[(height - 1 - x, y)].clone()
+        });
+    }
+    res
+}
+
 #[derive(Clone)]
-struct Tile {
+pub struct Tile {
     bits: Array2<i8>,
 }
 
 impl Tile {
-    fn new(input: &str) -> Tile {
+    /// Parses rows of `#` (set) and anything else (unset), which must all be
+    /// the same, non-zero width.
+    pub fn new(input: &str) -> Result<Tile, Box<dyn Error>> {
         let mut v = Vec::<i8>::new();
-        let mut width = 0;
+        let mut width = None;
         let mut height = 0;
         for line in input.lines() {
             //println!("Processing line {} of length {}", height, line.len());
-            width = line.len();
+            let len = line.chars().count();
+            if *width.get_or_insert(len) != len {
+                return Err(format!(
+                    "Row {} has a different width",
+                    height + 1
+                )
+                .into());
+            }
             height += 1;
             for c in line.chars() {
                 v.push(if c == '#' { 1 } else { 0 });
             }
         }
         //println!("height {} width {} arr.len {} str.len {}", height, width, v.len(), input.len());
-        Tile {
-            bits: Array::from_shape_vec((height, width), v).unwrap(),
+        let width = width.unwrap_or(0);
+        if width == 0 {
+            return Err("Empty tile".into());
         }
+        Ok(Tile {
+            bits: Array::from_shape_vec((height, width), v)?,
+        })
     }
 
-    fn monster() -> Tile {
-        Tile::new(
-            "\
-.                 # 
-#    ##    ##    ###
- #  #  #  #  #  #   ",
-        )
+    /// Finds every placement of `patterns`, in every orientation, within
+    /// this image. Matches may overlap.
+    pub fn find(&self, patterns: &[Pattern]) -> Vec<PatternMatch> {
+        let mut res = Vec::new();
+        for (i, pattern) in patterns.iter().enumerate() {
+            for (ori, oriented) in pattern.variants() {
+                let (height, width) = oriented.bits.dim();
+                let (image_height, image_width) = self.bits.dim();
+                if height > image_height || width > image_width {
+                    continue;
+                }
+                for row in 0..=(image_height - height) {
+                    for col in 0..=(image_width - width) {
+                        if oriented.matches_at(self, row, col) {
+                            res.push(PatternMatch {
+                                pattern: i,
+                                orientation: ori,
+                                row,
+                                col,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        res
     }
 
-    fn contains(&self, other: &Tile, pos: &Pos) -> bool {
-        for y in 0..other.bits.dim().0 {
-            for x in 0..other.bits.dim().1 {
-                if other.bits[(y, x)] == 1
-                    && self.bits.get((y + pos.1 as usize, x + pos.0 as usize))
-                        != Some(&1)
-                {
-                    return false;
+    /// The cells of this image covered by the set cells of `matches`.
+    pub fn covered(
+        &self,
+        patterns: &[Pattern],
+        matches: &[PatternMatch],
+    ) -> Array2<bool> {
+        let mut mask = Array2::<bool>::default(self.bits.dim());
+        for m in matches {
+            let oriented = patterns[m.pattern].tile.transformed(&m.orientation);
+            for ((y, x), cell) in oriented.bits.indexed_iter() {
+                if *cell == 1 {
+                    mask[(m.row + y, m.col + x)] = true;
                 }
             }
         }
-        true
+        mask
     }
 
     fn transformed(&self, ori: &Orientation) -> Tile {
-        assert_eq!(self.bits.dim().0, self.bits.dim().1);
-        let dim = self.bits.dim().0;
-
-        let mut bits = if ori.0 {
-            let mut flipped = Array2::<i8>::zeros(self.bits.dim());
-            for x in 0..dim {
-                for y in 0..dim {
-                    flipped[(x, y)] = self.bits[((dim - x - 1), y)]
-                }
-            }
-            flipped
-        } else {
-            self.bits.clone()
-        };
+        Tile {
+            bits: transform(&self.bits, ori),
+        }
+    }
 
-        // rotate `ori.1` times
-        for _i in 0..ori.1 {
-            let mut rotated = Array2::<i8>::zeros(bits.dim());
-            for x in 0..dim {
-                for y in 0..dim {
-                    rotated[(x, y)] = bits[(dim - 1 - y, x)]
-                }
-            }
-            bits = rotated
+    /// Orientations that preserve this tile's shape.
+    fn orientations(&self) -> &'static [Orientation] {
+        if self.bits.dim().0 == self.bits.dim().1 {
+            &ORIENTATIONS
+        } else {
+            &RECT_ORIENTATIONS
         }
+    }
 
-        Tile { bits }
+    fn sides(&self) -> [TileSide; 4] {
+        [self.top(), self.bottom(), self.left(), self.right()]
     }
 
     fn top(&self) -> TileSide {
@@ -141,51 +200,408 @@ impl Tile {
     }
 }
 
-struct TileSet {
+/// Something to search for in an image. Cells are 1 where the image must be
+/// set, 0 where it must be clear, and -1 where it doesn't matter.
+pub struct Pattern {
+    tile: Tile,
+}
+
+/// Where a pattern was found in an image, with the pattern's orientation
+/// relative to the image.
+#[derive(Debug, Eq, PartialEq)]
+pub struct PatternMatch {
+    /// Index into the searched patterns.
+    pub pattern: usize,
+    pub orientation: Orientation,
+    /// Top-left corner of the oriented pattern within the image.
+    pub row: usize,
+    pub col: usize,
+}
+
+impl Pattern {
+    /// Parses a pattern in which `#` must be set, `wildcard` can be
+    /// anything, and any other character must be clear.
+    pub fn new(input: &str, wildcard: char) -> Result<Pattern, Box<dyn Error>> {
+        let width = input.lines().map(|line| line.chars().count()).max();
+        let width = width.ok_or("Empty pattern")?;
+        let mut v = Vec::<i8>::new();
+        let mut height = 0;
+        for line in input.lines() {
+            height += 1;
+            // Pad short lines with wildcards.
+            for c in line.chars().chain(std::iter::repeat(wildcard)).take(width)
+            {
+                v.push(match c {
+                    '#' => 1,
+                    c if c == wildcard => -1,
+                    _ => 0,
+                });
+            }
+        }
+        Ok(Pattern {
+            tile: Tile {
+                bits: Array::from_shape_vec((height, width), v)?,
+            },
+        })
+    }
+
+    pub fn monster() -> Pattern {
+        Pattern::new(
+            concat!(
+                "                  # \n",
+                "#    ##    ##    ###\n",
+                " #  #  #  #  #  #   ",
+            ),
+            ' ',
+        )
+        .unwrap()
+    }
+
+    /// Number of cells that must be set.
+    pub fn size(&self) -> usize {
+        self.tile.bits.iter().filter(|x| **x == 1).count()
+    }
+
+    /// The distinct orientations of this pattern. Symmetric patterns have
+    /// fewer than 8, and we don't want to report the same match twice.
+    fn variants(&self) -> Vec<(Orientation, Tile)> {
+        let mut res: Vec<(Orientation, Tile)> = Vec::new();
+        for ori in &ORIENTATIONS {
+            let tile = self.tile.transformed(ori);
+            if !res.iter().any(|(_, t)| t.bits == tile.bits) {
+                res.push((*ori, tile));
+            }
+        }
+        res
+    }
+}
+
+impl Tile {
+    // Whether this tile, interpreted as a pattern, matches `image` with its
+    // top-left corner at (`row`, `col`). The pattern must fit in the image.
+    fn matches_at(&self, image: &Tile, row: usize, col: usize) -> bool {
+        self.bits.indexed_iter().all(|((y, x), cell)| {
+            *cell == -1 || image.bits[(row + y, col + x)] == *cell
+        })
+    }
+}
+
+// Colors used when writing images.
+const SEA: Rgb = [0x10, 0x30, 0x70];
+const WAVE: Rgb = [0x60, 0xa0, 0xe0];
+const HIGHLIGHT: Rgb = [0x30, 0xe0, 0x50];
+
+impl Tile {
+    /// Renders in the puzzle's `#`/`.` format, with cells set in
+    /// `highlight` (e.g. from `covered`) shown as `O`.
+    pub fn to_ascii(&self, highlight: Option<&Array2<bool>>) -> String {
+        let mut res = String::new();
+        for (y, row) in self.bits.outer_iter().enumerate() {
+            for (x, bit) in row.iter().enumerate() {
+                res.push(match highlight.map(|h| h[(y, x)]) {
+                    Some(true) => 'O',
+                    _ if *bit == 1 => '#',
+                    _ => '.',
+                });
+            }
+            res.push('\n');
+        }
+        res
+    }
+
+    // RGB pixels, with each cell scaled up to `scale` x `scale` pixels.
+    fn pixels(
+        &self,
+        highlight: Option<&Array2<bool>>,
+        scale: usize,
+    ) -> (usize, usize, Vec<u8>) {
+        let (height, width) = self.bits.dim();
+        let data =
+            raster::scaled_pixels(height, width, scale, |pos| match highlight
+                .map(|h| h[pos])
+            {
+                Some(true) => HIGHLIGHT,
+                _ if self.bits[pos] == 1 => WAVE,
+                _ => SEA,
+            });
+        (height * scale, width * scale, data)
+    }
+
+    /// Writes a binary PPM image; see `to_ascii`.
+    pub fn write_ppm<W: Write>(
+        &self,
+        highlight: Option<&Array2<bool>>,
+        scale: usize,
+        w: W,
+    ) -> std::io::Result<()> {
+        let (height, width, data) = self.pixels(highlight, scale);
+        raster::write_ppm(w, height, width, &data)
+    }
+
+    /// Writes a PNG image; see `to_ascii`.
+    pub fn write_png<W: Write>(
+        &self,
+        highlight: Option<&Array2<bool>>,
+        scale: usize,
+        w: W,
+    ) -> Result<(), Box<dyn Error>> {
+        let (height, width, data) = self.pixels(highlight, scale);
+        let mut encoder = png::Encoder::new(w, width as u32, height as u32);
+        encoder.set_color(png::ColorType::RGB);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&data)?;
+        Ok(())
+    }
+}
+
+pub struct TileSet {
     tiles: HashMap<TileId, Tile>,
 }
 
+/// An assembled puzzle, along with anything that didn't fit.
+pub struct Solution {
+    /// Positions are normalized so that the top-left cell is (0, 0).
+    puzzle: Puzzle,
+    /// Size of the assembled grid in tiles, as (rows, cols).
+    pub dim: (usize, usize),
+    /// Tiles that didn't fit anywhere in the assembled grid, sorted.
+    pub unplaced: Vec<TileId>,
+    /// Cells of the assembled grid that no tile could fill, sorted.
+    pub missing: Vec<Pos>,
+}
+
+impl Solution {
+    /// The assembled image, with tile borders stripped.
+    pub fn image(&self) -> Tile {
+        TileSet::render(&self.puzzle)
+    }
+
+    /// The assembled tiles with their borders intact, each row of tiles
+    /// headed by their ids. Missing tiles are filled with `?`. Columns are
+    /// widened to fit ids longer than a tile.
+    pub fn tiles_ascii(&self) -> String {
+        let (_, tile) = self.puzzle.values().next().unwrap();
+        let (tile_height, tile_width) = tile.bits.dim();
+        let width = self
+            .puzzle
+            .values()
+            .map(|(tid, _)| tid.to_string().len())
+            .fold(tile_width, usize::max);
+        let mut res = String::new();
+        for y in 0..self.dim.0 as i16 {
+            let tiles: Vec<_> = (0..self.dim.1 as i16)
+                .map(|x| self.puzzle.get(&(y, x)))
+                .collect();
+            if y > 0 {
+                res.push('\n');
+            }
+            let ids: Vec<_> = tiles
+                .iter()
+                .map(|tile| match tile {
+                    Some((tid, _)) => format!("{:<w$}", tid, w = width),
+                    None => " ".repeat(width),
+                })
+                .collect();
+            res.push_str(ids.join(" ").trim_end());
+            res.push('\n');
+            for row in 0..tile_height {
+                let rows: Vec<_> = tiles
+                    .iter()
+                    .map(|tile| {
+                        let row: String = match tile {
+                            Some((_, tile)) => tile
+                                .bits
+                                .row(row)
+                                .iter()
+                                .map(|b| if *b == 1 { '#' } else { '.' })
+                                .collect(),
+                            None => "?".repeat(tile_width),
+                        };
+                        format!("{:<w$}", row, w = width)
+                    })
+                    .collect();
+                res.push_str(rows.join(" ").trim_end());
+                res.push('\n');
+            }
+        }
+        res
+    }
+
+    /// Which tile is where, indexed by (row, col).
+    pub fn layout(&self) -> Array2<Option<TileId>> {
+        Array2::from_shape_fn(self.dim, |(y, x)| {
+            self.tile_at(&(y as i16, x as i16))
+        })
+    }
+
+    pub fn tile_at(&self, pos: &Pos) -> Option<TileId> {
+        self.puzzle.get(pos).map(|(tid, _)| *tid)
+    }
+
+    /// Ids of the corner tiles; an error if any corner is missing.
+    pub fn corners(&self) -> Result<[TileId; 4], Box<dyn Error>> {
+        let maxy = self.dim.0 as i16 - 1;
+        let maxx = self.dim.1 as i16 - 1;
+        let mut res = [0; 4];
+        for (i, pos) in [(0, 0), (0, maxx), (maxy, 0), (maxy, maxx)]
+            .iter()
+            .enumerate()
+        {
+            res[i] = self
+                .tile_at(pos)
+                .ok_or_else(|| format!("No tile in corner {:?}", pos))?;
+        }
+        Ok(res)
+    }
+}
+
 impl TileSet {
-    fn new(input: &str) -> TileSet {
+    pub fn new(input: &str) -> Result<TileSet, Box<dyn Error>> {
         let mut tiles = HashMap::<TileId, Tile>::new();
-        for id_and_tile_input in input.trim_end().split("\n\n") {
-            //println!("Parsing '{}'", id_and_tile_input);
-            let mut tile_input_it = id_and_tile_input.splitn(2, '\n');
-            let id_input = tile_input_it.next().unwrap();
-            let tile_input = tile_input_it.next().unwrap();
+        let mut shape = None;
+        for record in records(input) {
+            let (id_input, tile_input) = record
+                .text
+                .split_once('\n')
+                .ok_or_else(|| format!("Line {}: Empty tile", record.line))?;
 
             let id = id_input
+                .trim_end()
                 .strip_prefix("Tile ")
-                .unwrap()
-                .strip_suffix(":")
-                .unwrap()
-                .parse()
-                .unwrap();
-            let tile = Tile::new(tile_input);
-            tiles.insert(id, tile);
+                .and_then(|s| s.strip_suffix(":"))
+                .ok_or_else(|| {
+                    format!(
+                        "Line {}: Bad tile header: {}",
+                        record.line, id_input
+                    )
+                })?
+                .parse()?;
+
+            let tile = Tile::new(tile_input)
+                .map_err(|e| format!("Tile {}: {}", id, e))?;
+
+            // All tiles must share a shape. Rectangular tiles may only be
+            // flipped or rotated by 180 degrees, so one given transposed
+            // doesn't belong.
+            let (height, width) = tile.bits.dim();
+            match shape {
+                None => shape = Some((height, width)),
+                Some(s) if s == (height, width) => (),
+                Some(s) => {
+                    return Err(format!(
+                        "Tile {} is {}x{}; expected {}x{}",
+                        id, height, width, s.0, s.1
+                    )
+                    .into())
+                }
+            }
+
+            if tiles.insert(id, tile).is_some() {
+                return Err(format!("Duplicate tile id {}", id).into());
+            }
         }
-        TileSet { tiles }
+        Ok(TileSet { tiles })
+    }
+
+    /// Whether any side of tile `tid` could line up with a side of another
+    /// tile. Tiles for which this is false can't be part of the image.
+    fn has_match(&self, tid: TileId) -> bool {
+        let tile = &self.tiles[&tid];
+        let sides: Vec<_> = tile
+            .sides()
+            .iter()
+            .flat_map(|side| vec![side.reversed(), TileSide(side.0.clone())])
+            .collect();
+        self.tiles
+            .iter()
+            .filter(|(other_tid, _)| **other_tid != tid)
+            .any(|(_, other)| other.sides().iter().any(|s| sides.contains(s)))
     }
 
-    fn solve(&self) -> Puzzle {
+    /// Assembles as much of the image as possible. Tiles whose sides don't
+    /// match any other tile are left out, as are any that can't be placed
+    /// consistently with the rest; grid cells that end up empty are reported
+    /// as missing. Like the puzzle itself, this assumes that borders which line
+    /// up are unique, so that the first complete assembly found is the right
+    /// one.
+    pub fn solve(&self) -> Result<Solution, Box<dyn Error>> {
+        let mut ids: Vec<TileId> = self.tiles.keys().copied().collect();
+        ids.sort_unstable();
+        if ids.is_empty() {
+            return Err("No tiles".into());
+        }
+
+        // Precompute each candidate tile in each of its valid orientations.
+        let variants: HashMap<TileId, Vec<Tile>> = ids
+            .iter()
+            .filter(|tid| ids.len() == 1 || self.has_match(**tid))
+            .map(|tid| {
+                let tile = &self.tiles[tid];
+                let variants = tile
+                    .orientations()
+                    .iter()
+                    .map(|ori| tile.transformed(ori))
+                    .collect();
+                (*tid, variants)
+            })
+            .collect();
+        if variants.is_empty() {
+            return Err("No two tiles share a border".into());
+        }
+        let seed = *variants.keys().min().unwrap();
+
         let mut puzzle = Puzzle::new();
-        let (tid, tile) = self.tiles.iter().next().unwrap();
-        puzzle.insert((0, 0), (*tid, tile.clone()));
+        puzzle.insert((0, 0), (seed, self.tiles[&seed].clone()));
         let mut empty_pos = HashSet::new();
         empty_pos.insert((1, 0));
         empty_pos.insert((-1, 0));
         empty_pos.insert((0, 1));
         empty_pos.insert((0, -1));
-        if !self.solve_helper(&mut puzzle, empty_pos) {
-            panic!();
+        let mut best = puzzle.clone();
+        self.solve_helper(&variants, &mut puzzle, empty_pos, &mut best);
+
+        // Normalize positions to start at (0, 0).
+        let miny = best.keys().map(|(y, _)| *y).min().unwrap();
+        let minx = best.keys().map(|(_, x)| *x).min().unwrap();
+        let maxy = best.keys().map(|(y, _)| *y).max().unwrap();
+        let maxx = best.keys().map(|(_, x)| *x).max().unwrap();
+        let puzzle: Puzzle = best
+            .into_iter()
+            .map(|((y, x), tile)| ((y - miny, x - minx), tile))
+            .collect();
+        let dim = ((maxy - miny + 1) as usize, (maxx - minx + 1) as usize);
+
+        let mut missing = Vec::new();
+        for y in 0..dim.0 as i16 {
+            for x in 0..dim.1 as i16 {
+                if !puzzle.contains_key(&(y, x)) {
+                    missing.push((y, x));
+                }
+            }
         }
-        puzzle
+        let placed: HashSet<TileId> =
+            puzzle.values().map(|(tid, _)| *tid).collect();
+        let unplaced = ids
+            .into_iter()
+            .filter(|tid| !placed.contains(tid))
+            .collect();
+
+        Ok(Solution {
+            puzzle,
+            dim,
+            unplaced,
+            missing,
+        })
     }
 
+    // Places tiles from `variants` into `puzzle`, recording the largest
+    // assembly seen in `best`. Returns true once every tile has been placed.
     fn solve_helper(
         &self,
+        variants: &HashMap<TileId, Vec<Tile>>,
         puzzle: &mut Puzzle,
         empty_positions: HashSet<Pos>,
+        best: &mut Puzzle,
     ) -> bool {
         let mut empty_positions = empty_positions;
         while !empty_positions.is_empty() {
@@ -198,16 +614,14 @@ impl TileSet {
             }
 
             // Find a tile that fits in this position.
-            for (tid, tile) in &self.tiles {
+            for (tid, tiles) in variants {
                 // If this tile is already in use, skip.
                 if puzzle.values().any(|(used_tid, _)| used_tid == tid) {
                     continue;
                 }
 
                 // Try each orientation for this tile.
-                for ori in &ORIENTATIONS {
-                    let tile = tile.transformed(ori);
-
+                for tile in tiles {
                     // If any neighbor doesn't match, skip.
                     if let Some((_, neighbor)) =
                         puzzle.get(&(empty_pos.0 + 1, empty_pos.1))
@@ -239,7 +653,7 @@ impl TileSet {
                     }
 
                     // Insert into puzzle; add new empty positions
-                    puzzle.insert(empty_pos, (*tid, tile));
+                    puzzle.insert(empty_pos, (*tid, tile.clone()));
 
                     let mut new_empty_pos = empty_positions.clone();
                     // Push all neighboring positions. Don't bother to check if they're already
@@ -248,7 +662,8 @@ impl TileSet {
                     new_empty_pos.insert((empty_pos.0 - 1, empty_pos.1));
                     new_empty_pos.insert((empty_pos.0, empty_pos.1 + 1));
                     new_empty_pos.insert((empty_pos.0, empty_pos.1 - 1));
-                    if self.solve_helper(puzzle, new_empty_pos) {
+                    if self.solve_helper(variants, puzzle, new_empty_pos, best)
+                    {
                         return true;
                     }
 
@@ -259,8 +674,12 @@ impl TileSet {
             // No tile fit this position; possibly because we're at an edge. Move to the next position.
         }
 
-        // No more empty positions. We've finished the puzzle iff all tiles have been placed.
-        puzzle.len() == self.tiles.len()
+        // No more empty positions. Remember this assembly if it's the
+        // biggest so far; we're finished iff all tiles have been placed.
+        if puzzle.len() > best.len() {
+            *best = puzzle.clone();
+        }
+        puzzle.len() == variants.len()
     }
 
     fn render(puzzle: &Puzzle) -> Tile {
@@ -280,82 +699,182 @@ impl TileSet {
         let tile_height = tile.bits.dim().0 as i16 - 2;
         let tile_width = tile.bits.dim().1 as i16 - 2;
 
-        // Render
+        // Render. Cells with no tile are left blank.
         let width = ((maxx - minx + 1) * tile_width) as usize;
         let height = ((maxy - miny + 1) * tile_height) as usize;
         let mut bits = Array2::<i8>::zeros((height, width));
-        //assert_eq!(width, height);
         for y in 0..height {
             for x in 0..width {
                 let tile_pos = (
                     y as i16 / tile_height + miny,
                     x as i16 / tile_width + minx,
                 );
-                let (_, tile) = puzzle.get(&tile_pos).unwrap();
-                bits[(y, x)] = tile.bits[(
-                    ((y as i16 % tile_height) + 1) as usize,
-                    ((x as i16 % tile_width) + 1) as usize,
-                )];
+                if let Some((_, tile)) = puzzle.get(&tile_pos) {
+                    bits[(y, x)] = tile.bits[(
+                        ((y as i16 % tile_height) + 1) as usize,
+                        ((x as i16 % tile_width) + 1) as usize,
+                    )];
+                }
             }
         }
         Tile { bits }
     }
 }
 
-pub fn part1(input: &str) -> u64 {
-    let ts = TileSet::new(input);
-    let puzzle = ts.solve();
-
-    // Find edges
-    let mut minx = i16::MAX;
-    let mut miny = i16::MAX;
-    let mut maxx = i16::MIN;
-    let mut maxy = i16::MIN;
-    for (x, y) in puzzle.keys() {
-        minx = std::cmp::min(minx, *x);
-        miny = std::cmp::min(miny, *y);
-        maxx = std::cmp::max(maxx, *x);
-        maxy = std::cmp::max(maxy, *y);
-    }
-    //println!("{:?}", puzzle);
-
-    // Will panic if we ended up with a non-rectangular shape.
-    [(minx, miny), (minx, maxy), (maxx, miny), (maxx, maxy)]
-        .iter()
-        .map(|pos| puzzle.get(pos).unwrap().0 as u64)
-        .product()
+/// A puzzle cut from a known image.
+pub struct Generated {
+    /// Tiles in the day-20 input format, in random order.
+    pub input: String,
+    /// Which tile came from where, indexed by (row, col).
+    pub layout: Array2<TileId>,
 }
 
-fn monsters_in(tile: &Tile) -> u64 {
-    let monster = Tile::monster();
-    ORIENTATIONS
-        .iter()
-        .map(|ori| {
-            let tile = tile.transformed(ori);
-            let mut count = 0;
-            for x in 0..tile.bits.dim().1 {
-                for y in 0..tile.bits.dim().0 {
-                    if tile.contains(&monster, &(x as i16, y as i16)) {
-                        count += 1
-                    }
+type Borders = Array2<Vec<i8>>;
+
+// Random borders for a grid of `rows` x `cols` tiles of `tile_dim`; returns
+// the rows shared between vertically adjacent tiles, and the columns shared
+// between horizontally adjacent tiles. Neighboring borders share their end
+// points, and no border matches any other, even reversed.
+fn generate_borders<R: Rng>(
+    rows: usize,
+    cols: usize,
+    tile_dim: (usize, usize),
+    rng: &mut R,
+) -> Result<(Borders, Borders), Box<dyn Error>> {
+    'attempt: for _attempt in 0..100 {
+        let corners = Array2::from_shape_fn((rows + 1, cols + 1), |_| {
+            rng.gen_range(0..2)
+        });
+
+        // Pick each border in turn, retrying any that clash with one that's
+        // already been picked.
+        let mut seen = HashSet::new();
+        let mut border = |len: usize, start: i8, end: i8| {
+            for _try in 0..100 {
+                let mut v = vec![start];
+                v.extend((0..len - 2).map(|_| rng.gen_range(0..2)));
+                v.push(end);
+                let mut reversed = v.clone();
+                reversed.reverse();
+                if v != reversed
+                    && !seen.contains(&v)
+                    && !seen.contains(&reversed)
+                {
+                    seen.insert(reversed);
+                    seen.insert(v.clone());
+                    return Some(v);
                 }
             }
-            count
-        })
-        .max()
+            None
+        };
+        let mut horizontal = Array2::from_elem((rows + 1, cols), Vec::new());
+        for ((y, x), b) in horizontal.indexed_iter_mut() {
+            match border(tile_dim.1, corners[(y, x)], corners[(y, x + 1)]) {
+                Some(v) => *b = v,
+                None => continue 'attempt,
+            }
+        }
+        let mut vertical = Array2::from_elem((rows, cols + 1), Vec::new());
+        for ((y, x), b) in vertical.indexed_iter_mut() {
+            match border(tile_dim.0, corners[(y, x)], corners[(y + 1, x)]) {
+                Some(v) => *b = v,
+                None => continue 'attempt,
+            }
+        }
+        return Ok((horizontal, vertical));
+    }
+    Err("Couldn't generate unique borders; try bigger tiles".into())
+}
+
+/// Cuts `image` into tiles of `tile_dim` (height, width), including the
+/// borders that get stripped when the puzzle is solved. Borders are random,
+/// but unique, so that the puzzle has a single solution. Each tile is
+/// randomly flipped and rotated, keeping its shape, and gets a random 4-digit
+/// id.
+pub fn generate<R: Rng>(
+    image: &Tile,
+    tile_dim: (usize, usize),
+    rng: &mut R,
+) -> Result<Generated, Box<dyn Error>> {
+    if tile_dim.0 < 3 || tile_dim.1 < 3 {
+        return Err("Tiles must be at least 3x3".into());
+    }
+    let inner = (tile_dim.0 - 2, tile_dim.1 - 2);
+    let (height, width) = image.bits.dim();
+    if height == 0 || height % inner.0 != 0 || width % inner.1 != 0 {
+        return Err(format!(
+            "{}x{} image can't be cut into {}x{} tiles",
+            height, width, tile_dim.0, tile_dim.1
+        )
+        .into());
+    }
+    let (rows, cols) = (height / inner.0, width / inner.1);
+    if rows * cols > 9000 {
+        return Err("Too many tiles for 4-digit ids".into());
+    }
+
+    let (horizontal, vertical) = generate_borders(rows, cols, tile_dim, rng)?;
+    let ids = rand::seq::index::sample(rng, 9000, rows * cols);
+    let layout = Array::from_shape_vec(
+        (rows, cols),
+        ids.iter().map(|i| i + 1000).collect(),
+    )?;
+
+    let mut tiles = Vec::new();
+    for ((y, x), id) in layout.indexed_iter() {
+        let mut bits = Array2::<i8>::zeros(tile_dim);
+        bits.slice_mut(s![1..tile_dim.0 - 1, 1..tile_dim.1 - 1])
+            .assign(&image.bits.slice(s![
+                y * inner.0..(y + 1) * inner.0,
+                x * inner.1..(x + 1) * inner.1
+            ]));
+        for i in 0..tile_dim.1 {
+            bits[(0, i)] = horizontal[(y, x)][i];
+            bits[(tile_dim.0 - 1, i)] = horizontal[(y + 1, x)][i];
+        }
+        for i in 0..tile_dim.0 {
+            bits[(i, 0)] = vertical[(y, x)][i];
+            bits[(i, tile_dim.1 - 1)] = vertical[(y, x + 1)][i];
+        }
+        let tile = Tile { bits };
+        let ori = tile.orientations().choose(rng).unwrap();
+        tiles.push((*id, tile.transformed(ori)));
+    }
+    tiles.shuffle(rng);
+
+    let input = tiles
+        .iter()
+        .map(|(id, tile)| format!("Tile {}:\n{}", id, tile.to_ascii(None)))
+        .collect::<Vec<_>>()
+        .join("\n");
+    Ok(Generated { input, layout })
+}
+
+pub fn part1(input: &str) -> u64 {
+    let ts = TileSet::new(input).unwrap();
+    let solution = ts.solve().unwrap();
+    solution
+        .corners()
         .unwrap()
+        .iter()
+        .map(|tid| *tid as u64)
+        .product()
 }
 
 pub fn part2(input: &str) -> u64 {
-    let ts = TileSet::new(input);
-    let puzzle = ts.solve();
-    let image = TileSet::render(&puzzle);
-
-    let monster_count = monsters_in(&image);
-    //println!("monster count: {}", monster_count);
-    image.bits.iter().filter(|x| x == &&1).count() as u64
-        - Tile::monster().bits.iter().filter(|x| x == &&1).count() as u64
-            * monster_count
+    let ts = TileSet::new(input).unwrap();
+    let solution = ts.solve().unwrap();
+    let image = solution.image();
+
+    let patterns = [Pattern::monster()];
+    let matches = image.find(&patterns);
+    let covered = image.covered(&patterns, &matches);
+    image
+        .bits
+        .iter()
+        .zip(covered.iter())
+        .filter(|(bit, covered)| **bit == 1 && !**covered)
+        .count() as u64
 }
 
 #[cfg(test)]
@@ -373,7 +892,7 @@ Tile 2311:
 ..#....#..
 ###...#.#.
 ..###..###";
-    let ts = TileSet::new(input);
+    let ts = TileSet::new(input).unwrap();
 
     assert_eq!(
         ts.tiles.get(&2311).unwrap().top().0,
@@ -408,7 +927,7 @@ Tile 2311:
 ..#....#..
 ###...#.#.
 ..###..###";
-    let ts = TileSet::new(input);
+    let ts = TileSet::new(input).unwrap();
     assert_eq!(
         ts.tiles
             .get(&2311)
@@ -471,7 +990,7 @@ Tile 2:
 ##..#.....
 ..##.#..#.
 ";
-    let ts = TileSet::new(input);
+    let ts = TileSet::new(input).unwrap();
     assert_eq!(
         ts.tiles.get(&1).unwrap().right(),
         ts.tiles.get(&2).unwrap().left()
@@ -594,20 +1113,29 @@ Tile 3079:
 
 #[cfg(test)]
 #[test]
-fn test_contains() {
+fn test_matches_at() {
     let input = "\
 ..                 # 
 .#    ##    ##    ###
 . #  #  #  #  #  #   ";
-    let tile = Tile::new(input);
-    assert!(!tile.contains(&Tile::monster(), &(0, 0)));
-    assert!(tile.contains(&Tile::monster(), &(1, 0)));
-    assert!(!tile.contains(&Tile::monster(), &(0, 1)));
+    let tile = Tile::new(input).unwrap();
+    let monster = Pattern::monster().tile;
+    assert!(!monster.matches_at(&tile, 0, 0));
+    assert!(monster.matches_at(&tile, 0, 1));
+    assert_eq!(
+        tile.find(&[Pattern::monster()]),
+        vec![PatternMatch {
+            pattern: 0,
+            orientation: (false, 0),
+            row: 0,
+            col: 1
+        }]
+    );
 }
 
 #[cfg(test)]
 #[test]
-fn test_monsters_in() {
+fn test_find_monsters() {
     let input = "\
 .#.#..#.##...#.##..#####
 ###....#.#....#..#......
@@ -633,8 +1161,12 @@ fn test_monsters_in() {
 #.##..#..#...#..####...#
 .#.###..##..##..####.##.
 ...###...##...#...#..###";
-    let tile = Tile::new(input);
-    assert_eq!(monsters_in(&tile), 2);
+    let tile = Tile::new(input).unwrap();
+    let patterns = [Pattern::monster()];
+    let matches = tile.find(&patterns);
+    assert_eq!(matches.len(), 2);
+    let covered = tile.covered(&patterns, &matches);
+    assert_eq!(covered.iter().filter(|c| **c).count(), 30);
 }
 
 #[cfg(test)]
@@ -652,7 +1184,8 @@ fn test_render() {
 #.#.#
 .....
 #.#.#",
-            ),
+            )
+            .unwrap(),
         ),
     );
     assert_eq!(
@@ -663,6 +1196,7 @@ fn test_render() {
 .#.
 ..."
         )
+        .unwrap()
         .bits
     );
 
@@ -677,7 +1211,8 @@ fn test_render() {
 #####
 .....
 #.#.#",
-            ),
+            )
+            .unwrap(),
         ),
     );
     assert_eq!(
@@ -691,6 +1226,7 @@ fn test_render() {
 ###
 ..."
         )
+        .unwrap()
         .bits
     );
 
@@ -705,7 +1241,8 @@ fn test_render() {
 #####
 ..#..
 #.#.#",
-            ),
+            )
+            .unwrap(),
         ),
     );
     puzzle.insert(
@@ -719,7 +1256,8 @@ fn test_render() {
 #####
 ..#..
 #.#.#",
-            ),
+            )
+            .unwrap(),
         ),
     );
     assert_eq!(
@@ -733,6 +1271,7 @@ fn test_render() {
 ######
 ....#."
         )
+        .unwrap()
         .bits
     );
 }
@@ -848,8 +1387,8 @@ Tile 3079:
 ..#.###...
 ..#.......
 ..#.###...";
-    let ts = TileSet::new(input);
-    let puzzle = ts.solve();
+    let ts = TileSet::new(input).unwrap();
+    let puzzle = ts.solve().unwrap().puzzle;
     let image = TileSet::render(&puzzle);
     let expected = Tile::new(
         "\
@@ -878,7 +1417,8 @@ Tile 3079:
 .#.###..##..##..####.##.
 ...###...##...#...#..###
 ",
-    );
+    )
+    .unwrap();
     for ori in &ORIENTATIONS {
         println!("{:?}\n", image.transformed(ori).bits);
     }
@@ -888,3 +1428,384 @@ Tile 3079:
 
     assert_eq!(part2(input), 273);
 }
+
+#[cfg(test)]
+const RECT_INPUT: &str = "\
+Tile 11:
+###.#.##
+#.....##
+..#####.
+#.....#.
+.##....#
+
+Tile 12:
+##......
+.####.##
+.#.###..
+#.#.#...
+###...##
+
+Tile 13:
+#.#....#
+..#####.
+.##...#.
+#.#####.
+.#..##..
+
+Tile 21:
+##...##.
+###.#.#.
+#.#.##..
+##.#####
+#....##.
+
+Tile 22:
+##......
+#.###.##
+##.#.###
+#..#..##
+##..##.#
+
+Tile 23:
+.###...#
+##..#.##
+#......#
+.##....#
+..##..#.
+
+Tile 99:
+########
+........
+........
+........
+########";
+
+#[cfg(test)]
+#[test]
+fn test_rectangular() {
+    // 5x8 tiles in a 2x3 grid, some flipped or rotated by 180 degrees; tile
+    // 99 doesn't belong anywhere.
+    let ts = TileSet::new(RECT_INPUT).unwrap();
+    let solution = ts.solve().unwrap();
+    assert_eq!(solution.dim, (2, 3));
+    assert_eq!(solution.unplaced, vec![99]);
+    assert!(solution.missing.is_empty());
+    assert_eq!(solution.tile_at(&(0, 1)), Some(12));
+    assert_eq!(solution.tile_at(&(1, 1)), Some(22));
+    assert_eq!(solution.corners().unwrap(), [11, 13, 21, 23]);
+    assert_eq!(TileSet::render(&solution.puzzle).bits.dim(), (6, 18));
+    assert_eq!(part1(RECT_INPUT), 11 * 13 * 21 * 23);
+
+    // A tile rotated by 90 degrees changes shape, so is rejected.
+    assert!(TileSet::new("Tile 1:\n###\n...\n\nTile 2:\n##\n..\n.#").is_err());
+}
+
+#[cfg(test)]
+const MISSING_INPUT: &str = "\
+Tile 11:
+#.#...#.
+....##..
+##....##
+####..##
+.#..###.
+....#.##
+#....#..
+##..####
+
+Tile 12:
+....##..
+.#...#..
+####..#.
+#..#####
+..###.##
+#..###..
+.#.....#
+##...###
+
+Tile 13:
+##.##...
+..##.#..
+..###..#
+..#####.
+###..##.
+..#...#.
+.#..#.#.
+###..###
+
+Tile 21:
+##..####
+.##.#.#.
+###....#
+#.###...
+...#.#.#
+..#..##.
+#.#.#.#.
+.#..##..
+
+Tile 23:
+#...#..#
+#..###.#
+#..#.#.#
+#......#
+#...#...
+#..##.##
+..#..#.#
+##.#.###
+
+Tile 31:
+.#..##..
+.###....
+#..#.#..
+..#...##
+#.####..
+##.#.#..
+##.##...
+##.#..#.
+
+Tile 32:
+.......#
+..#..#.#
+.##.####
+.#..##..
+#.##.##.
+.#...#.#
+..##..#.
+..####.#
+
+Tile 33:
+##.#.###
+.###.##.
+#.###...
+.#....#.
+...##...
+#..#.#..
+#.##...#
+#.##...#";
+
+#[cfg(test)]
+#[test]
+fn test_missing() {
+    // 3x3 grid with the middle tile missing.
+    let ts = TileSet::new(MISSING_INPUT).unwrap();
+    let solution = ts.solve().unwrap();
+    assert_eq!(solution.dim, (3, 3));
+    assert!(solution.unplaced.is_empty());
+    assert_eq!(solution.missing, vec![(1, 1)]);
+    assert_eq!(solution.corners().unwrap(), [11, 13, 31, 33]);
+
+    // Without a corner the grid keeps its shape, but has no corner product.
+    let without_corner = MISSING_INPUT.split("\n\nTile 33:").next().unwrap();
+    let solution = TileSet::new(without_corner).unwrap().solve().unwrap();
+    assert_eq!(solution.dim, (3, 3));
+    assert_eq!(solution.missing, vec![(1, 1), (2, 2)]);
+    assert!(solution.corners().is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn test_errors() {
+    // Inconsistent tile shapes.
+    assert!(TileSet::new("Tile 1:\n##\n..\n\nTile 2:\n###\n...\n..#").is_err());
+    // Ragged tile.
+    assert!(TileSet::new("Tile 1:\n##\n.").is_err());
+    // Widths count characters, not bytes.
+    assert!(TileSet::new("Tile 1:\n#é\n..").is_ok());
+    assert!(Tile::new("").is_err());
+    // Duplicate id.
+    assert!(TileSet::new("Tile 1:\n##\n..\n\nTile 1:\n##\n..").is_err());
+    // Bad header.
+    assert!(TileSet::new("Tile one:\n##\n..").is_err());
+
+    // No tiles that fit together.
+    let ts = TileSet::new("Tile 1:\n###\n#.#\n###\n\nTile 2:\n...\n...\n...")
+        .unwrap();
+    assert!(ts.solve().is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn test_find_overlapping() {
+    let image = Tile::new(
+        "\
+###
+##.
+...",
+    )
+    .unwrap();
+
+    // Symmetric patterns are only reported once per placement.
+    let patterns = [Pattern::new("##", ' ').unwrap()];
+    let matches = image.find(&patterns);
+    assert_eq!(matches.len(), 5);
+    // Overlapping matches don't double-count cells.
+    let covered = image.covered(&patterns, &matches);
+    assert_eq!(covered.iter().filter(|c| **c).count(), 5);
+    assert!(!covered[(1, 2)]);
+
+    // Multiple patterns at once; `.` must be clear and `?` is a wildcard.
+    let patterns = [
+        Pattern::new("#.", '?').unwrap(),
+        Pattern::new("#?.\n..?", '?').unwrap(),
+    ];
+    let matches = image.find(&patterns);
+    assert_eq!(
+        matches.iter().filter(|m| m.pattern == 0).count(),
+        // Right of (1, 1), below (1, 0), below (1, 1), below (0, 2).
+        4
+    );
+    assert_eq!(
+        matches
+            .iter()
+            .filter(|m| m.pattern == 1)
+            .map(|m| (m.row, m.col))
+            .collect::<Vec<_>>(),
+        vec![(1, 0)]
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn test_export() {
+    let image = Tile::new(
+        "\
+..................#.
+#....##....##....###
+.#..#..#..#..#..#...
+#..#................",
+    )
+    .unwrap();
+    let patterns = [Pattern::monster()];
+    let covered = image.covered(&patterns, &image.find(&patterns));
+
+    assert_eq!(Tile::new(&image.to_ascii(None)).unwrap().bits, image.bits);
+    assert_eq!(
+        image.to_ascii(Some(&covered)),
+        "\
+..................O.
+O....OO....OO....OOO
+.O..O..O..O..O..O...
+#..#................
+"
+    );
+
+    let mut ppm = Vec::new();
+    image.write_ppm(Some(&covered), 2, &mut ppm).unwrap();
+    let header = b"P6\n40 8\n255\n";
+    assert!(ppm.starts_with(header));
+    assert_eq!(ppm.len(), header.len() + 40 * 8 * 3);
+    // Scaled-up pixels of the first row: sea, sea, ..., monster.
+    assert_eq!(ppm[header.len()..header.len() + 3], SEA);
+    assert_eq!(ppm[header.len() + 36 * 3..header.len() + 37 * 3], HIGHLIGHT);
+
+    let mut png_data = Vec::new();
+    image.write_png(None, 3, &mut png_data).unwrap();
+    let (info, _) = png::Decoder::new(png_data.as_slice()).read_info().unwrap();
+    assert_eq!((info.width, info.height), (60, 12));
+}
+
+#[cfg(test)]
+#[test]
+fn test_tiles_ascii() {
+    let mut puzzle = Puzzle::new();
+    puzzle.insert((0, 0), (1, Tile::new("#.#\n...\n#.#").unwrap()));
+    puzzle.insert((0, 1), (23, Tile::new("###\n...\n###").unwrap()));
+    puzzle.insert((1, 1), (4567, Tile::new("..#\n.##\n###").unwrap()));
+    let solution = Solution {
+        puzzle,
+        dim: (2, 2),
+        unplaced: vec![],
+        missing: vec![(1, 0)],
+    };
+    assert_eq!(
+        solution.tiles_ascii(),
+        "\
+1    23
+#.#  ###
+...  ...
+#.#  ###
+
+     4567
+???  ..#
+???  .##
+???  ###
+"
+    );
+
+    // An id wider than the tiles widens every column.
+    let mut puzzle = Puzzle::new();
+    puzzle.insert((0, 0), (1, Tile::new("#.\n.#").unwrap()));
+    puzzle.insert((0, 1), (4567, Tile::new("##\n..").unwrap()));
+    puzzle.insert((0, 2), (89, Tile::new("..\n##").unwrap()));
+    let solution = Solution {
+        puzzle,
+        dim: (1, 3),
+        unplaced: vec![],
+        missing: vec![],
+    };
+    assert_eq!(
+        solution.tiles_ascii(),
+        "\
+1    4567 89
+#.   ##   ..
+.#   ..   ##
+"
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn test_generate() {
+    use rand::SeedableRng;
+    let mut rng = rand::rngs::StdRng::seed_from_u64(20);
+    let image = Tile::new(
+        "\
+.#.#..#.##...#.##..#####
+###....#.#....#..#......
+##.##.###.#.#..######...
+###.#####...#.#####.#..#
+##.#....#.##.####...#.##
+...########.#....#####.#
+....#..#...##..#.#.###..
+.####...#..#.....#......
+#..#.##..#..###.#.##....
+#.####..#.####.#.#.###..
+###.#.#...#.######.#..##
+#.####....##..########.#
+##..##.#...#...#.#.#.#..
+...#..#..#.#.##..###.###
+.#.#....#.##.#...###.##.
+###.#...#..#.##.######..
+.#.#.###.##.##.#..#.##..
+.####.###.#...###.#..#.#
+..#.#..#..#.#.#.####.###
+#..####...#.#.#.###.###.
+#####..#####...###....##
+#.##..#..#...#..####...#
+.#.###..##..##..####.##.
+...###...##...#...#..###",
+    )
+    .unwrap();
+
+    for tile_dim in &[(10, 10), (8, 14), (26, 6)] {
+        let generated = generate(&image, *tile_dim, &mut rng).unwrap();
+        let solution = TileSet::new(&generated.input).unwrap().solve().unwrap();
+        assert!(solution.unplaced.is_empty());
+        assert!(solution.missing.is_empty());
+
+        // The solution may come out flipped or rotated as a whole, but
+        // should otherwise be an exact reconstruction.
+        let ori = ORIENTATIONS
+            .iter()
+            .find(|ori| solution.image().transformed(ori).bits == image.bits)
+            .unwrap();
+        assert_eq!(
+            transform(&solution.layout(), ori),
+            generated.layout.map(|id| Some(*id))
+        );
+
+        assert_eq!(part2(&generated.input), 273);
+    }
+
+    assert!(generate(&image, (7, 7), &mut rng).is_err());
+    assert!(generate(&image, (2, 10), &mut rng).is_err());
+}
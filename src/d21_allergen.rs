@@ -13,7 +13,7 @@ struct Food<'a> {
 }
 
 impl<'a> Food<'a> {
-    fn new(input: &'a str) -> Food {
+    fn new(input: &'a str) -> Food<'a> {
         let mut tokens_it = input.split_ascii_whitespace();
         let mut ingredients = HashSet::<Ingredient>::new();
         loop {
@@ -27,8 +27,8 @@ impl<'a> Food<'a> {
 
         let mut allergens = HashSet::<Allergen>::new();
         for mut token in tokens_it {
-            token = token.strip_suffix(',').or(Some(token)).unwrap();
-            token = token.strip_suffix(')').or(Some(token)).unwrap();
+            token = token.strip_suffix(',').unwrap_or(token);
+            token = token.strip_suffix(')').unwrap_or(token);
             allergens.insert(Allergen(token));
         }
         Food {
@@ -38,7 +38,7 @@ impl<'a> Food<'a> {
     }
 }
 
-fn parse_foods(input: &str) -> Vec<Food> {
+fn parse_foods(input: &str) -> Vec<Food<'_>> {
     let mut foods = Vec::<Food>::new();
     for line in input.lines() {
         foods.push(Food::new(line));
@@ -53,8 +53,7 @@ fn id_unsafe_ingredients<'a>(
         HashMap::<Allergen, HashSet<Ingredient>>::new();
     for food in foods {
         for allergen in &food.allergens {
-            if let Some(ingredients) = allergens2ingredients.get_mut(&allergen)
-            {
+            if let Some(ingredients) = allergens2ingredients.get_mut(allergen) {
                 *ingredients = ingredients
                     .intersection(&food.ingredients)
                     .copied()
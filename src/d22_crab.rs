@@ -1,3 +1,4 @@
+use crate::records::records;
 use std::collections::{HashSet, VecDeque};
 
 type Card = u8;
@@ -30,6 +31,8 @@ fn play_game(d1: Deck, d2: Deck) -> Deck {
 }
 
 // Returns true iff player 1 wins, and returns winning deck.
+// `game_num` is only used by the (commented-out) debug output.
+#[allow(clippy::only_used_in_recursion)]
 fn play_rec_game(game_num: u32, d1: Deck, d2: Deck) -> (bool, Deck) {
     let mut prev_states = HashSet::new();
     let mut d1 = d1;
@@ -84,17 +87,15 @@ fn play_rec_game(game_num: u32, d1: Deck, d2: Deck) -> (bool, Deck) {
 }
 
 fn score_deck(d: &Deck) -> u64 {
-    let mut multiplier = 1;
     let mut score = 0;
-    for c in d.iter().rev() {
+    for (multiplier, c) in (1..).zip(d.iter().rev()) {
         score += *c as u64 * multiplier;
-        multiplier += 1;
     }
     score
 }
 
 fn parse(input: &str) -> (Deck, Deck) {
-    let mut deck_input = input.split("\n\n");
+    let mut deck_input = records(input).map(|r| r.text);
     let parse_deck = |di: &str| -> Deck {
         let mut deck = Deck::new();
         for line in di.lines().skip(1) {
@@ -111,9 +111,8 @@ impl BigCircle {
         let max = circle.iter().copied().max().unwrap();
 
         // Create with dummy data.
-        let mut next_cup = Vec::new();
         // We need one more than `max` so that we can index directly by cup #.
-        next_cup.resize((max + 1) as usize, 0);
+        let mut next_cup = vec![0; (max + 1) as usize];
 
         // Fill from circle
         let mut prev_cup = circle[0];
@@ -211,13 +210,7 @@ mod testing {
     fn test_example() {
         let input = "389125467";
         let circle = parse_circle(input);
-        assert_eq!(
-            circle,
-            [3, 8, 9, 1, 2, 5, 4, 6, 7]
-                .iter()
-                .copied()
-                .collect::<Circle>()
-        );
+        assert_eq!(circle, [3, 8, 9, 1, 2, 5, 4, 6, 7].to_vec());
         assert_eq!(one_move(&circle), parse_circle("289154673"));
 
         assert_eq!(canonicalize(&parse_circle("583741926")), "92658374");
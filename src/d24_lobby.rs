@@ -1,4 +1,5 @@
-use std::collections::{HashMap, HashSet};
+use crate::automaton::{Automaton, Hex, Sparse};
+use std::collections::HashSet;
 
 type Coord = (i32, i32);
 
@@ -43,32 +44,13 @@ pub fn part1(input: &str) -> usize {
 }
 
 pub fn part2(input: &str) -> usize {
-    let mut floor = parse_all_coords(input);
-
-    let directions = [(-1, 0), (1, 0), (0, 1), (1, 1), (-1, -1), (0, -1)];
-    for _ in 0..100 {
-        let mut neighbor_counts = HashMap::<Coord, u8>::new();
-        for coord in &floor {
-            for d in &directions {
-                let mut neighbor = *coord;
-                neighbor.0 += d.0;
-                neighbor.1 += d.1;
-                let entry = neighbor_counts.entry(neighbor).or_insert(0);
-                *entry += 1;
-            }
-        }
-
-        // Consider whether each tile in the new floor ought to be set based on its neighbor count.
-        // Implicitly unsets tiles with no neighbors.
-        let mut new_floor = HashSet::new();
-        for (coord, count) in &neighbor_counts {
-            if *count == 2 || (*count == 1 && floor.contains(coord)) {
-                new_floor.insert(*coord);
-            }
-        }
-        floor = new_floor;
-    }
-    floor.len()
+    // A black tile with zero or more than 2 black neighbors is flipped to
+    // white; a white tile with exactly 2 black neighbors is flipped to black.
+    let mut floor =
+        Sparse::new(Hex, "B2/S12".parse().unwrap(), parse_all_coords(input))
+            .unwrap();
+    floor.run(100);
+    floor.population()
 }
 
 #[cfg(test)]
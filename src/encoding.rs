@@ -1,49 +1,177 @@
-fn has_sum_operands(nums: &[u64], i: usize, n: usize) -> bool {
-    for j in 1..=n {
-        for k in (j + 1)..=n {
-            if nums[i - j] + nums[i - k] == nums[i] {
-                return true;
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::io::BufRead;
+use std::ops::Range;
+
+/// Checks a stream of numbers, each of which must be the sum of two of the
+/// `preamble` numbers before it. Keeps the counts of all pair sums in the
+/// window, so each number costs O(`preamble`) rather than O(`preamble`²).
+pub struct XmasValidator {
+    preamble: usize,
+    window: VecDeque<u64>,
+    // How many pairs of (distinct positions in) the window sum to each key.
+    // Pairs whose sum overflows can't match any number, so aren't counted.
+    sums: HashMap<u64, usize>,
+}
+
+impl XmasValidator {
+    pub fn new(preamble: usize) -> XmasValidator {
+        XmasValidator {
+            preamble,
+            window: VecDeque::with_capacity(preamble + 1),
+            sums: HashMap::new(),
+        }
+    }
+
+    /// Whether `x` would be valid as the next number. Numbers in the preamble
+    /// always are.
+    pub fn is_valid(&self, x: u64) -> bool {
+        self.window.len() < self.preamble || self.sums.contains_key(&x)
+    }
+
+    /// Adds the next number, returning whether it was valid.
+    pub fn push(&mut self, x: u64) -> bool {
+        let valid = self.is_valid(x);
+        if self.window.len() == self.preamble {
+            if let Some(oldest) = self.window.pop_front() {
+                for sum in
+                    self.window.iter().filter_map(|y| oldest.checked_add(*y))
+                {
+                    let count = self.sums.get_mut(&sum).unwrap();
+                    *count -= 1;
+                    if *count == 0 {
+                        self.sums.remove(&sum);
+                    }
+                }
+            }
+        }
+        if self.preamble > 0 {
+            for sum in self.window.iter().filter_map(|y| x.checked_add(*y)) {
+                *self.sums.entry(sum).or_insert(0) += 1;
             }
+            self.window.push_back(x);
         }
+        valid
     }
-    false
+}
+
+/// Every invalid number and its 0-based position.
+pub fn invalid<I: IntoIterator<Item = u64>>(
+    nums: I,
+    preamble: usize,
+) -> Vec<(usize, u64)> {
+    let mut validator = XmasValidator::new(preamble);
+    nums.into_iter()
+        .enumerate()
+        .filter(|(_, x)| !validator.push(*x))
+        .collect()
+}
+
+/// Like `invalid`, reading a number per line from `reader`.
+pub fn read_invalid<R: BufRead>(
+    reader: R,
+    preamble: usize,
+) -> Result<Vec<(usize, u64)>, Box<dyn Error>> {
+    let mut validator = XmasValidator::new(preamble);
+    let mut res = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let x = line?
+            .trim()
+            .parse()
+            .map_err(|e| format!("Line {}: {}", i + 1, e))?;
+        if !validator.push(x) {
+            res.push((i, x));
+        }
+    }
+    Ok(res)
 }
 
 pub fn parse(input: &str) -> Vec<u64> {
     input.lines().map(|x| x.parse::<u64>().unwrap()).collect()
 }
 
-pub fn part1(nums: &[u64], n: usize) -> u64 {
-    let idx = (n..nums.len())
-        .find(|i| !has_sum_operands(&nums, *i, n))
-        .unwrap();
-    nums[idx]
+/// The first invalid number, if any.
+pub fn part1(nums: &[u64], n: usize) -> Option<u64> {
+    let mut validator = XmasValidator::new(n);
+    nums.iter().copied().find(|x| !validator.push(*x))
 }
 
-pub fn part2(nums: &[u64], n: usize) -> usize {
-    let target = part1(&nums, n);
-    let mut lo = 0;
-    let mut hi = 1;
-    let mut sum = nums[lo] + nums[hi];
-    // Structure of this loop was definitely inspired by seeing
-    // https://github.com/AxlLind/AdventOfCode2020/blob/537508ca5abc08198ed65cb8240ac9f174d37b7a/src/bin/09.rs#L18
-    while sum != target {
-        // Consider bumping `lo` up, being careful not to pass `hi`.
-        if sum > target && ((lo + 1) <= hi) {
-            sum -= nums[lo];
-            lo += 1;
-        } else {
-            hi += 1;
-            sum += nums[hi];
+/// Every range of at least two contiguous numbers summing to `target`,
+/// ordered by start then end. Works with negative numbers too, by looking up
+/// prefix sums in a hash.
+pub fn ranges_summing_to<T: Copy + Into<i128>>(
+    nums: &[T],
+    target: T,
+) -> Vec<Range<usize>> {
+    let target: i128 = target.into();
+    // Positions at which each prefix sum occurs.
+    let mut prefixes: HashMap<i128, Vec<usize>> = HashMap::new();
+    let mut ranges = Vec::new();
+    let mut prefix = 0;
+    let mut prev_prefix = None;
+    for (end, x) in nums.iter().enumerate() {
+        // Only make the prefix before the previous number available, so that
+        // ranges have at least two numbers.
+        if let Some((pos, prev)) = prev_prefix.take() {
+            prefixes.entry(prev).or_default().push(pos);
+        }
+        prev_prefix = Some((end, prefix));
+        prefix += (*x).into();
+        if let Some(starts) = prefixes.get(&(prefix - target)) {
+            ranges.extend(starts.iter().map(|start| *start..end + 1));
         }
     }
-    let seq = &nums[lo..=hi];
-    (seq.iter().max().unwrap() + seq.iter().min().unwrap()) as usize
+    ranges.sort_unstable_by_key(|r| (r.start, r.end));
+    ranges
+}
+
+/// The sum of the smallest and largest numbers in the first range (see
+/// `ranges_summing_to`) that sums to `target`.
+pub fn weakness(nums: &[u64], target: u64) -> Option<u64> {
+    // Numbers aren't negative, so the first end at which a range reaches
+    // `target` only moves forward as the start does.
+    let target = u128::from(target);
+    let mut end = 0;
+    // The sum of nums[start..end].
+    let mut sum = 0u128;
+    for start in 0..nums.len() {
+        while end < nums.len() && (end < start + 2 || sum < target) {
+            sum += u128::from(nums[end]);
+            end += 1;
+        }
+        if end >= start + 2 && sum == target {
+            let seq = &nums[start..end];
+            return Some(seq.iter().max().unwrap() + seq.iter().min().unwrap());
+        }
+        sum -= u128::from(nums[start]);
+    }
+    None
+}
+
+/// Takes the invalid number found by `part1`.
+pub fn part2(nums: &[u64], target: u64) -> Result<u64, Box<dyn Error>> {
+    weakness(nums, target)
+        .ok_or_else(|| format!("No contiguous range sums to {}", target).into())
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::io::Cursor;
+
+    // The straightforward check, for comparison.
+    fn has_sum_operands(nums: &[u64], i: usize, n: usize) -> bool {
+        for j in 1..=n {
+            for k in (j + 1)..=n {
+                if nums[i - j] + nums[i - k] == nums[i] {
+                    return true;
+                }
+            }
+        }
+        false
+    }
 
     #[test]
     fn test_part1() {
@@ -71,7 +199,88 @@ mod test {
 576",
         );
 
-        assert_eq!(part1(&input, 5), 127);
-        assert_eq!(part2(&input, 5), 62);
+        assert_eq!(part1(&input, 5), Some(127));
+        assert_eq!(part1(&input[..14], 5), None);
+        assert_eq!(part2(&input, 127).unwrap(), 62);
+        assert_eq!(ranges_summing_to(&input, 127), vec![2..6]);
+        assert!(part2(&input, 1).is_err());
+    }
+
+    #[test]
+    fn test_invalid() {
+        let nums = [1, 2, 3, 4, 9, 7, 16, 1, 2];
+        assert_eq!(
+            invalid(nums.iter().copied(), 2),
+            vec![(3, 4), (4, 9), (5, 7), (7, 1), (8, 2)]
+        );
+        assert_eq!(
+            invalid(nums.iter().copied(), 3),
+            vec![(4, 9), (7, 1), (8, 2)]
+        );
+        assert_eq!(
+            read_invalid(Cursor::new("1\n2\n3\n4\n9\n7\n16\n1\n2\n"), 3)
+                .unwrap(),
+            vec![(4, 9), (7, 1), (8, 2)]
+        );
+        assert!(read_invalid(Cursor::new("1\nx\n"), 3).is_err());
+
+        // Equal numbers at different positions make a pair.
+        assert_eq!(invalid(vec![5, 5, 10], 2), vec![]);
+        // Sums that would overflow are skipped.
+        let big = u64::MAX / 2;
+        assert_eq!(
+            invalid(vec![u64::MAX, big, big + 1, u64::MAX, u64::MAX - 1], 3),
+            vec![(4, u64::MAX - 1)]
+        );
+        // Nothing is a sum of an empty window.
+        assert_eq!(invalid(vec![1, 2], 0), vec![(0, 1), (1, 2)]);
+
+        let mut rng = StdRng::seed_from_u64(47);
+        for preamble in 1..8 {
+            let nums: Vec<u64> =
+                (0..200).map(|_| rng.gen_range(0..20)).collect();
+            let expected: Vec<_> = (preamble..nums.len())
+                .filter(|i| !has_sum_operands(&nums, *i, preamble))
+                .map(|i| (i, nums[i]))
+                .collect();
+            assert_eq!(invalid(nums.iter().copied(), preamble), expected);
+        }
+    }
+
+    #[test]
+    fn test_ranges_summing_to() {
+        assert_eq!(
+            ranges_summing_to(&[5u64, 5, 5, 5], 10),
+            vec![0..2, 1..3, 2..4]
+        );
+        // Single numbers don't count.
+        assert_eq!(ranges_summing_to(&[10u64, 1, 9], 10), vec![1..3]);
+        assert!(ranges_summing_to::<u64>(&[], 0).is_empty());
+        assert_eq!(weakness(&[1, 2], 4), None);
+        assert_eq!(
+            ranges_summing_to(&[3i64, -3, 4, -1, 0, 2], 0),
+            vec![0..2, 1..4, 1..5]
+        );
+        assert_eq!(ranges_summing_to(&[0i32, 0, 0], 0), vec![0..2, 0..3, 1..3]);
+
+        // `weakness` agrees with the first of all the ranges.
+        let mut rng = StdRng::seed_from_u64(48);
+        for _ in 0..500 {
+            let len = rng.gen_range(0..12);
+            let nums: Vec<u64> =
+                (0..len).map(|_| rng.gen_range(0..5)).collect();
+            let target = rng.gen_range(0..12);
+            let expected = ranges_summing_to(&nums, target).first().map(|r| {
+                let seq = &nums[r.clone()];
+                seq.iter().max().unwrap() + seq.iter().min().unwrap()
+            });
+            assert_eq!(
+                weakness(&nums, target),
+                expected,
+                "{:?} {}",
+                nums,
+                target
+            );
+        }
     }
 }
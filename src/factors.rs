@@ -1,53 +1,346 @@
-/// Finds `n` elements of `xs` that sum to `target`. Returned in reverse order.
+use std::convert::TryInto;
+use std::error::Error;
+
+/// Finds combinations of `k` values that sum to a target, for any primitive
+/// integer type, as long as the values fit in an `i128`. Values are sorted
+/// once; the search then fixes all but two values, pruning with prefix sums,
+/// and finds the last two with a two-pointer scan.
 ///
 /// ```
-/// use aoc2020::factors::find_sum_factors;
+/// use aoc2020::factors::KSum;
 ///
-/// // n=1
-/// assert_eq!(find_sum_factors(1,
-///                             &[1721, 979, 366, 299, 675, 1456],
-///                             2020),
-///            None);
-/// assert_eq!(find_sum_factors(1,
-///                             &[1721, 979, 366, 299, 675, 1456],
-///                             366),
-///            Some(vec![366]));
+/// let report = KSum::new(&[1721, 979, 366, 299, 675, 1456]).unwrap();
+/// assert_eq!(report.find(2, 2020), Some(vec![299, 1721]));
+/// assert_eq!(report.find(3, 2020), Some(vec![366, 675, 979]));
+/// assert_eq!(report.find(4, 2020), None);
 ///
-/// // n=2
-/// assert_eq!(find_sum_factors(2,
-///                             &[1721, 979, 366, 299, 675, 1456],
-///                             2020),
-///            Some(vec![299, 1721]));
-/// assert_eq!(find_sum_factors(2,
-///                             &[],
-///                             2020),
-///            None);
-/// assert_eq!(find_sum_factors(2,
-///                             &[1],
-///                             2020),
-///            None);
+/// // Solutions are distinct by value, but a value may be used as often as it
+/// // occurs.
+/// let ksum = KSum::new(&[3u8, 1, 2, 2, 1, 0]).unwrap();
+/// assert_eq!(
+///     ksum.solutions(2, 4).collect::<Vec<_>>(),
+///     vec![vec![1, 3], vec![2, 2]]
+/// );
+/// // 0+1+3, 0+2+2 and 1+1+2.
+/// assert_eq!(ksum.count(3, 4), 3);
+/// assert_eq!(KSum::new(&[2, 3]).unwrap().count(2, 4), 0);
 ///
-/// // n=3
-/// assert_eq!(find_sum_factors(3,
-///                             &[1721, 979, 366, 299, 675, 1456],
-///                             2020),
-///            Some(vec![675, 366, 979]));
+/// // Signed values, and the degenerate sizes.
+/// let ksum = KSum::new(&[-5i64, 5, 0, -5, 10]).unwrap();
+/// assert_eq!(ksum.solutions(3, 0).collect::<Vec<_>>(), vec![vec![-5, -5, 10], vec![-5, 0, 5]]);
+/// assert_eq!(ksum.find(1, 10), Some(vec![10]));
+/// assert_eq!(ksum.count(1, -5), 1);
+/// assert_eq!(ksum.count(0, 0), 1);
+/// assert_eq!(ksum.count(6, 0), 0);
+///
+/// // Any integer type, as long as the values fit in an `i128`.
+/// let ksum = KSum::new(&[1usize, 2, 3]).unwrap();
+/// assert_eq!(ksum.find(2, 5), Some(vec![2, 3]));
+/// let ksum = KSum::new(&[1u128, u128::MAX / 4]).unwrap();
+/// assert_eq!(ksum.count(2, u128::MAX / 4 + 1), 1);
+/// // Targets out of range have no solutions.
+/// assert_eq!(ksum.count(1, u128::MAX), 0);
+/// assert!(KSum::new(&[u128::MAX]).is_err());
 /// ```
-pub fn find_sum_factors(n: u32, xs: &[i32], target: i32) -> Option<Vec<i32>> {
-    match n {
-        1 => xs.iter().find(|x| **x == target).map(|x| vec![*x]),
-        _ => {
-            for i in 1..xs.len() {
-                let (head, tail) = xs.split_at(i);
-                let head: i32 = *head.last().unwrap();
-                if let Some(mut v) =
-                    find_sum_factors(n - 1, tail, target - head)
+pub struct KSum<T> {
+    sorted: Vec<T>,
+    // The sorted values as `i128`s.
+    values: Vec<i128>,
+    // prefix[i] is the sum of the first `i` sorted values.
+    prefix: Vec<i128>,
+}
+
+impl<T: Copy + Ord + TryInto<i128>> KSum<T> {
+    /// Fails if a value doesn't fit in an `i128`.
+    pub fn new(xs: &[T]) -> Result<KSum<T>, Box<dyn Error>> {
+        let mut sorted = xs.to_vec();
+        sorted.sort_unstable();
+        let values = sorted
+            .iter()
+            .map(|x| (*x).try_into())
+            .collect::<Result<Vec<i128>, _>>()
+            .map_err(|_| "Value doesn't fit in an i128")?;
+        let mut prefix = Vec::with_capacity(sorted.len() + 1);
+        prefix.push(0);
+        for x in &values {
+            prefix.push(prefix.last().unwrap() + x);
+        }
+        Ok(KSum {
+            sorted,
+            values,
+            prefix,
+        })
+    }
+
+    /// Every distinct multiset of `k` values summing to `target`, each in
+    /// ascending order, in lexicographic order.
+    pub fn solutions(&self, k: usize, target: T) -> Solutions<'_, T> {
+        // A target that doesn't fit in an `i128` can't be reached.
+        let target = target.try_into().ok();
+        Solutions {
+            ksum: self,
+            k,
+            target: target.unwrap_or(0),
+            outer: vec![0; k.saturating_sub(2)],
+            lo: 0,
+            hi: 0,
+            started: false,
+            done: target.is_none(),
+        }
+    }
+
+    pub fn find(&self, k: usize, target: T) -> Option<Vec<T>> {
+        self.solutions(k, target).next()
+    }
+
+    /// The number of solutions, without building them.
+    pub fn count(&self, k: usize, target: T) -> usize {
+        let mut solutions = self.solutions(k, target);
+        let mut count = 0;
+        while solutions.advance() {
+            count += 1;
+        }
+        count
+    }
+}
+
+/// Iterator over the solutions of a `KSum`; see `KSum::solutions`.
+pub struct Solutions<'a, T> {
+    ksum: &'a KSum<T>,
+    k: usize,
+    target: i128,
+    // Indices of all but the last two values of the current candidate.
+    outer: Vec<usize>,
+    // The two-pointer scan for the last two values.
+    lo: usize,
+    hi: usize,
+    started: bool,
+    done: bool,
+}
+
+impl<'a, T: Copy + Ord> Solutions<'a, T> {
+    fn len(&self) -> usize {
+        self.ksum.sorted.len()
+    }
+
+    fn value(&self, i: usize) -> i128 {
+        self.ksum.values[i]
+    }
+
+    // The first index after `i` with a different value.
+    fn next_distinct(&self, i: usize) -> usize {
+        let sorted = &self.ksum.sorted;
+        let mut j = i + 1;
+        while j < sorted.len() && sorted[j] == sorted[i] {
+            j += 1;
+        }
+        j
+    }
+
+    // Points `outer[p]` at the first index from `from` that could still lead
+    // to a solution, given `outer[..p]`.
+    fn seek(&mut self, p: usize, from: usize) -> bool {
+        let len = self.len();
+        let prefix = &self.ksum.prefix;
+        // Values still to pick, including this one.
+        let remaining = self.k - p;
+        let base: i128 = self.outer[..p].iter().map(|i| self.value(*i)).sum();
+        let largest_rest = prefix[len] - prefix[len - (remaining - 1)];
+        let mut c = from;
+        while c + remaining <= len {
+            if base + prefix[c + remaining] - prefix[c] > self.target {
+                // Only gets bigger from here.
+                return false;
+            }
+            if base + self.value(c) + largest_rest >= self.target {
+                self.outer[p] = c;
+                return true;
+            }
+            c = self.next_distinct(c);
+        }
+        false
+    }
+
+    // Fills `outer[p..]`, starting with index `from` at `p` and backtracking
+    // as needed, then starts the two-pointer scan.
+    fn descend(&mut self, mut p: usize, mut from: usize) -> bool {
+        let depth = self.outer.len();
+        while p < depth {
+            if self.seek(p, from) {
+                from = self.outer[p] + 1;
+                p += 1;
+            } else if p == 0 {
+                return false;
+            } else {
+                p -= 1;
+                from = self.next_distinct(self.outer[p]);
+            }
+        }
+        self.lo = self.outer.last().map_or(0, |i| i + 1);
+        self.hi = self.len().saturating_sub(1);
+        true
+    }
+
+    // Moves to the next solution, leaving it in `outer`, `lo` and `hi`.
+    fn advance(&mut self) -> bool {
+        if self.done {
+            return false;
+        }
+        if self.k < 2 {
+            // Nothing to scan; at most one solution.
+            self.done = true;
+            return match self.k {
+                0 => self.target == 0,
+                _ => match self
+                    .ksum
+                    .values
+                    .iter()
+                    .position(|x| *x == self.target)
                 {
-                    v.push(head);
-                    return Some(v);
+                    Some(i) => {
+                        self.lo = i;
+                        true
+                    }
+                    None => false,
+                },
+            };
+        }
+        if !self.started {
+            self.started = true;
+            if self.len() < self.k || !self.descend(0, 0) {
+                self.done = true;
+                return false;
+            }
+        } else {
+            // Step past the previous solution's values.
+            self.lo = self.next_distinct(self.lo);
+            self.hi -= 1;
+        }
+        loop {
+            let base: i128 = self.outer.iter().map(|i| self.value(*i)).sum();
+            while self.lo < self.hi {
+                let sum = base + self.value(self.lo) + self.value(self.hi);
+                if sum == self.target {
+                    // Move `hi` to the first of its run of equal values, so
+                    // that stepping past it skips them all.
+                    while self.hi > self.lo + 1
+                        && self.ksum.sorted[self.hi - 1]
+                            == self.ksum.sorted[self.hi]
+                    {
+                        self.hi -= 1;
+                    }
+                    return true;
+                }
+                if sum < self.target {
+                    self.lo += 1;
+                } else {
+                    self.hi -= 1;
                 }
             }
-            None
+            let p = match self.outer.len() {
+                0 => {
+                    self.done = true;
+                    return false;
+                }
+                depth => depth - 1,
+            };
+            let from = self.next_distinct(self.outer[p]);
+            if !self.descend(p, from) {
+                self.done = true;
+                return false;
+            }
         }
     }
 }
+
+impl<'a, T: Copy + Ord> Iterator for Solutions<'a, T> {
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Vec<T>> {
+        if !self.advance() {
+            return None;
+        }
+        let sorted = &self.ksum.sorted;
+        let mut res: Vec<T> = self.outer.iter().map(|i| sorted[*i]).collect();
+        match self.k {
+            0 => (),
+            1 => res.push(sorted[self.lo]),
+            _ => {
+                res.push(sorted[self.lo]);
+                res.push(sorted[self.hi]);
+            }
+        }
+        Some(res)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::BTreeSet;
+
+    // All distinct sorted k-combinations summing to `target`, the slow way.
+    fn brute_force(xs: &[i32], k: usize, target: i32) -> Vec<Vec<i32>> {
+        let mut res = BTreeSet::new();
+        let mut idx: Vec<usize> = (0..k).collect();
+        if k > xs.len() {
+            return vec![];
+        }
+        loop {
+            let mut combination: Vec<i32> =
+                idx.iter().map(|i| xs[*i]).collect();
+            if combination.iter().sum::<i32>() == target {
+                combination.sort_unstable();
+                res.insert(combination);
+            }
+            // Next combination of indices.
+            let p = match (0..k).rev().find(|p| idx[*p] < xs.len() - k + p) {
+                Some(p) => p,
+                None => break,
+            };
+            idx[p] += 1;
+            for q in p + 1..k {
+                idx[q] = idx[q - 1] + 1;
+            }
+        }
+        res.into_iter().collect()
+    }
+
+    #[test]
+    fn test_against_brute_force() {
+        let mut rng = StdRng::seed_from_u64(46);
+        for _ in 0..500 {
+            let len = rng.gen_range(0..10);
+            let xs: Vec<i32> = (0..len).map(|_| rng.gen_range(-4..5)).collect();
+            let k = rng.gen_range(0..5);
+            let target = rng.gen_range(-6..7);
+            let ksum = KSum::new(&xs).unwrap();
+            let expected = brute_force(&xs, k, target);
+            assert_eq!(
+                ksum.solutions(k, target).collect::<Vec<_>>(),
+                expected,
+                "{:?} k={} target={}",
+                xs,
+                k,
+                target
+            );
+            assert_eq!(ksum.count(k, target), expected.len());
+        }
+    }
+
+    #[test]
+    fn test_large_report() {
+        // A day 1 style report, with one planted solution of each size.
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut xs: Vec<u32> = (0..100_000)
+            .map(|_| rng.gen_range(2021..1_000_000))
+            .collect();
+        xs.extend_from_slice(&[1000, 1020, 300, 700]);
+        let ksum = KSum::new(&xs).unwrap();
+        assert_eq!(ksum.find(2, 2020), Some(vec![1000, 1020]));
+        assert_eq!(ksum.find(3, 2020), Some(vec![300, 700, 1020]));
+        assert_eq!(ksum.count(4, 2020), 0);
+    }
+}
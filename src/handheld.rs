@@ -44,7 +44,7 @@ pub struct Handheld<'a> {
 }
 
 impl<'a> Handheld<'a> {
-    fn new(program: &'a [Insn]) -> Handheld {
+    fn new(program: &'a [Insn]) -> Handheld<'a> {
         Handheld {
             pc: 0,
             acc: 0,
@@ -21,12 +21,22 @@ fn main() {
         "8b" => Box::new(handheld::acc_after_fix(handheld::parse_program(
             Cursor::new(buf.as_bytes()),
         ))),
-        "9a" => Box::new(encoding::part1(&encoding::parse(&buf), 25)),
-        "9b" => Box::new(encoding::part2(&encoding::parse(&buf), 25)),
-        "10a" => Box::new(adapter::part1(&adapter::parse(&buf))),
-        "10b" => Box::new(adapter::part2(&adapter::parse(&buf))),
-        "11a" => Box::new(seating::part1(&seating::parse(&buf))),
-        "11b" => Box::new(seating::part2(&seating::parse(&buf))),
+        "9a" => Box::new(encoding::part1(&encoding::parse(&buf), 25).unwrap()),
+        "9b" => {
+            let nums = encoding::parse(&buf);
+            let target = encoding::part1(&nums, 25).unwrap();
+            Box::new(encoding::part2(&nums, target).unwrap())
+        }
+        "10a" => Box::new(
+            adapter::part1(&adapter::parse(&buf, adapter::DEVICE_OFFSET))
+                .unwrap(),
+        ),
+        "10b" => Box::new(adapter::part2(&adapter::parse(
+            &buf,
+            adapter::DEVICE_OFFSET,
+        ))),
+        "11a" => Box::new(seating::part1(&seating::parse(&buf)).unwrap()),
+        "11b" => Box::new(seating::part2(&seating::parse(&buf)).unwrap()),
         "12a" => Box::new(d12_rain::part1(&d12_rain::parse(&buf))),
         "12b" => Box::new(d12_rain::part2(&d12_rain::parse(&buf))),
         "13a" => Box::new(d13_bus::part1(&buf)),
@@ -0,0 +1,693 @@
+use crate::records::records;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+pub type Record = HashMap<String, String>;
+
+pub fn parse_key_val(s: &str) -> (&str, &str) {
+    let mut key_val_seq = s.splitn(2, ':');
+    // XXX: Yuck.
+    let key = key_val_seq.next().unwrap_or("");
+    let val = key_val_seq.next().unwrap_or("");
+    assert!(key_val_seq.next().is_none());
+    (key, val)
+}
+
+#[cfg(test)]
+#[test]
+fn test_parse_key_val() {
+    assert_eq!(parse_key_val("key:val"), ("key", "val"));
+    assert_eq!(parse_key_val("key:"), ("key", ""));
+    assert_eq!(parse_key_val("key"), ("key", ""));
+    assert_eq!(parse_key_val(""), ("", ""));
+}
+
+pub fn key_val_lines_to_hashmap<'a, I: Iterator<Item = &'a str>>(
+    it: I,
+) -> Record {
+    // Convert vector of lines into a flat iterator over tokens
+    let tokens = it.flat_map(|l| l.split_ascii_whitespace());
+
+    // Convert tokens to a hashmap
+    tokens
+        .map(parse_key_val)
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+#[test]
+fn test_parse_key_val_lines_to_hashmap() {
+    assert_eq!(
+        key_val_lines_to_hashmap(["k1:v1 k2:v2", "k3:v3"].iter().copied()),
+        [
+            ("k1".to_string(), "v1".to_string()),
+            ("k2".to_string(), "v2".to_string()),
+            ("k3".to_string(), "v3".to_string())
+        ]
+        .iter()
+        .cloned()
+        .collect()
+    );
+}
+
+/// What a field's value has to look like.
+#[derive(Debug, Clone)]
+pub enum Check {
+    Any,
+    /// An integer in the inclusive range.
+    Int {
+        min: i64,
+        max: i64,
+    },
+    /// An integer followed by one of the units, in that unit's inclusive
+    /// range.
+    Units(Vec<(String, i64, i64)>),
+    /// Matches the regex.
+    Pattern(Regex),
+    /// One of the listed values.
+    OneOf(Vec<String>),
+}
+
+// Parses "MIN-MAX" followed by an optional unit. Either bound may be
+// negative, as in "-10--5".
+fn parse_range(s: &str) -> Result<(i64, i64, &str), Box<dyn Error>> {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r"^(-?\d+)-(-?\d+)(\D*)$").unwrap();
+    }
+    let captures = RE.captures(s).ok_or_else(|| format!("Bad range {}", s))?;
+    let unit = captures.get(3).unwrap().as_str();
+    Ok((captures[1].parse()?, captures[2].parse()?, unit))
+}
+
+impl Check {
+    /// Returns why `value` fails the check, if it does.
+    pub fn violation(&self, value: &str) -> Option<String> {
+        match self {
+            Check::Any => None,
+            Check::Int { min, max } => match value.parse::<i64>() {
+                Ok(i) if (*min..=*max).contains(&i) => None,
+                Ok(_) => Some(format!("not in {}-{}", min, max)),
+                Err(_) => Some("not an integer".to_string()),
+            },
+            Check::Units(units) => {
+                let sign = if value.starts_with('-') { 1 } else { 0 };
+                let unit_start = value[sign..]
+                    .find(|c: char| !c.is_ascii_digit())
+                    .map_or(value.len(), |i| i + sign);
+                let (number, unit) = value.split_at(unit_start);
+                let (_, min, max) = match units.iter().find(|u| u.0 == unit) {
+                    Some(u) => u,
+                    None => return Some(format!("bad unit {:?}", unit)),
+                };
+                match number.parse::<i64>() {
+                    Ok(i) if (*min..=*max).contains(&i) => None,
+                    Ok(_) => Some(format!("not in {}-{}{}", min, max, unit)),
+                    Err(_) => Some("not an integer".to_string()),
+                }
+            }
+            Check::Pattern(re) => {
+                if re.is_match(value) {
+                    None
+                } else {
+                    Some(format!("doesn't match {}", re))
+                }
+            }
+            Check::OneOf(values) => {
+                if values.iter().any(|v| v == value) {
+                    None
+                } else {
+                    Some(format!("not one of {}", values.join(" ")))
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for Check {
+    type Err = Box<dyn Error>;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, ' ');
+        let kind = parts.next().unwrap();
+        let args = parts.next().unwrap_or("").trim();
+        match kind {
+            "any" => Ok(Check::Any),
+            "int" => {
+                let (min, max, unit) = parse_range(args)?;
+                if !unit.is_empty() {
+                    return Err(format!("Unexpected unit {}", unit).into());
+                }
+                Ok(Check::Int { min, max })
+            }
+            "units" => {
+                let units = args
+                    .split_ascii_whitespace()
+                    .map(|range| -> Result<_, Box<dyn Error>> {
+                        let (min, max, unit) = parse_range(range)?;
+                        if unit.is_empty() {
+                            return Err(
+                                format!("Missing unit in {}", range).into()
+                            );
+                        }
+                        Ok((unit.to_string(), min, max))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                if units.is_empty() {
+                    return Err("No units".into());
+                }
+                Ok(Check::Units(units))
+            }
+            "pattern" => Ok(Check::Pattern(Regex::new(args)?)),
+            "oneof" => Ok(Check::OneOf(
+                args.split_ascii_whitespace().map(String::from).collect(),
+            )),
+            _ => Err(format!("Unknown check {}", kind).into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Field {
+    pub name: String,
+    pub required: bool,
+    pub check: Check,
+}
+
+/// A problem with one field of a record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    Missing(String),
+    Invalid {
+        field: String,
+        value: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Violation::Missing(field) => write!(f, "missing {}", field),
+            Violation::Invalid {
+                field,
+                value,
+                reason,
+            } => write!(f, "{}:{} {}", field, value, reason),
+        }
+    }
+}
+
+/// The fields a record may have. Fields the schema doesn't know about are
+/// ignored.
+#[derive(Debug, Clone)]
+pub struct Schema {
+    pub fields: Vec<Field>,
+}
+
+lazy_static! {
+    static ref DAY4: Schema =
+        include_str!("passport_schema.txt").parse().unwrap();
+}
+
+impl Default for Schema {
+    /// The day 4 rules, from `passport_schema.txt`.
+    fn default() -> Schema {
+        DAY4.clone()
+    }
+}
+
+impl Schema {
+    /// The day 4 rules; see `default`.
+    pub fn day4() -> &'static Schema {
+        &DAY4
+    }
+
+    pub fn load<P: AsRef<std::path::Path>>(
+        path: P,
+    ) -> Result<Schema, Box<dyn Error>> {
+        std::fs::read_to_string(path)?.parse()
+    }
+
+    /// The same fields, without checking their values.
+    pub fn presence_only(&self) -> Schema {
+        Schema {
+            fields: self
+                .fields
+                .iter()
+                .map(|f| Field {
+                    check: Check::Any,
+                    ..f.clone()
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns every violation in `record`, in schema order.
+    pub fn validate(&self, record: &Record) -> Vec<Violation> {
+        self.violations(record, true)
+    }
+
+    /// Like `validate`, but only reports missing fields; the same as
+    /// validating against `presence_only`, without building it.
+    pub fn validate_presence(&self, record: &Record) -> Vec<Violation> {
+        self.violations(record, false)
+    }
+
+    fn violations(
+        &self,
+        record: &Record,
+        check_values: bool,
+    ) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        for field in &self.fields {
+            match record.get(&field.name) {
+                None if field.required => {
+                    violations.push(Violation::Missing(field.name.clone()))
+                }
+                None => (),
+                Some(_) if !check_values => (),
+                Some(value) => {
+                    if let Some(reason) = field.check.violation(value) {
+                        violations.push(Violation::Invalid {
+                            field: field.name.clone(),
+                            value: value.clone(),
+                            reason,
+                        });
+                    }
+                }
+            }
+        }
+        violations
+    }
+
+    pub fn is_valid(&self, record: &Record) -> bool {
+        self.validate(record).is_empty()
+    }
+}
+
+impl FromStr for Schema {
+    type Err = Box<dyn Error>;
+    /// One field per line; see `passport_schema.txt`. Blank lines and lines
+    /// starting with `#` are skipped.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = Vec::new();
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let field = (|| -> Result<Field, Box<dyn Error>> {
+                let mut parts = line.splitn(3, ' ');
+                let name = parts.next().unwrap().to_string();
+                let required = match parts.next() {
+                    Some("required") => true,
+                    Some("optional") => false,
+                    _ => return Err("Expected required or optional".into()),
+                };
+                let check = parts.next().ok_or("Missing check")?.parse()?;
+                Ok(Field {
+                    name,
+                    required,
+                    check,
+                })
+            })()
+            .map_err(|e| format!("Line {}: {}", i + 1, e))?;
+            fields.push(field);
+        }
+        Ok(Schema { fields })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Height {
+    Cm(u32),
+    In(u32),
+}
+
+impl fmt::Display for Height {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Height::Cm(h) => write!(f, "{}cm", h),
+            Height::In(h) => write!(f, "{}in", h),
+        }
+    }
+}
+
+impl FromStr for Height {
+    type Err = Box<dyn Error>;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(h) = s.strip_suffix("cm") {
+            Ok(Height::Cm(h.parse()?))
+        } else if let Some(h) = s.strip_suffix("in") {
+            Ok(Height::In(h.parse()?))
+        } else {
+            Err(format!("Bad height {}", s).into())
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+/// As `#rrggbb`.
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+impl FromStr for Rgb {
+    type Err = Box<dyn Error>;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s
+            .strip_prefix('#')
+            .filter(|hex| {
+                hex.len() == 6 && hex.bytes().all(|b| b.is_ascii_hexdigit())
+            })
+            .ok_or_else(|| format!("Bad colour {}", s))?;
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16);
+        Ok(Rgb {
+            r: channel(0)?,
+            g: channel(2)?,
+            b: channel(4)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EyeColor {
+    Amb,
+    Blu,
+    Brn,
+    Gry,
+    Grn,
+    Hzl,
+    Oth,
+}
+
+impl EyeColor {
+    pub const ALL: [EyeColor; 7] = [
+        EyeColor::Amb,
+        EyeColor::Blu,
+        EyeColor::Brn,
+        EyeColor::Gry,
+        EyeColor::Grn,
+        EyeColor::Hzl,
+        EyeColor::Oth,
+    ];
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            EyeColor::Amb => "amb",
+            EyeColor::Blu => "blu",
+            EyeColor::Brn => "brn",
+            EyeColor::Gry => "gry",
+            EyeColor::Grn => "grn",
+            EyeColor::Hzl => "hzl",
+            EyeColor::Oth => "oth",
+        }
+    }
+}
+
+impl fmt::Display for EyeColor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+impl FromStr for EyeColor {
+    type Err = Box<dyn Error>;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        EyeColor::ALL
+            .iter()
+            .copied()
+            .find(|e| e.code() == s)
+            .ok_or_else(|| format!("Bad eye colour {}", s).into())
+    }
+}
+
+/// A passport that satisfies the day 4 rules.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Passport {
+    pub birth_year: u16,
+    pub issue_year: u16,
+    pub expiration_year: u16,
+    pub height: Height,
+    pub hair_color: Rgb,
+    pub eye_color: EyeColor,
+    /// Kept as text, since leading zeros are significant.
+    pub passport_id: String,
+    pub country_id: Option<String>,
+}
+
+/// Validates against `Schema::day4`, failing with every violation.
+impl TryFrom<Record> for Passport {
+    type Error = Box<dyn Error>;
+    fn try_from(record: Record) -> Result<Self, Self::Error> {
+        let violations = Schema::day4().validate(&record);
+        if !violations.is_empty() {
+            let violations: Vec<_> =
+                violations.iter().map(|v| v.to_string()).collect();
+            return Err(violations.join(", ").into());
+        }
+        let field = |name: &str| record[name].as_str();
+        Ok(Passport {
+            birth_year: field("byr").parse()?,
+            issue_year: field("iyr").parse()?,
+            expiration_year: field("eyr").parse()?,
+            height: field("hgt").parse()?,
+            hair_color: field("hcl").parse()?,
+            eye_color: field("ecl").parse()?,
+            passport_id: field("pid").to_string(),
+            country_id: record.get("cid").cloned(),
+        })
+    }
+}
+
+/// Parses one passport's `key:value` fields, separated by spaces or newlines.
+impl FromStr for Passport {
+    type Err = Box<dyn Error>;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Passport::try_from(key_val_lines_to_hashmap(s.lines()))
+    }
+}
+
+/// All fields on one line, in the order of the day 4 schema.
+impl fmt::Display for Passport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "byr:{} iyr:{} eyr:{} hgt:{} hcl:{} ecl:{} pid:{}",
+            self.birth_year,
+            self.issue_year,
+            self.expiration_year,
+            self.height,
+            self.hair_color,
+            self.eye_color,
+            self.passport_id
+        )?;
+        if let Some(cid) = &self.country_id {
+            write!(f, " cid:{}", cid)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses each blank-line-separated passport in `input`.
+pub fn parse_batch(input: &str) -> Vec<Result<Passport, Box<dyn Error>>> {
+    records(input)
+        .map(|record| {
+            record
+                .text
+                .parse()
+                .map_err(|e| format!("Line {}: {}", record.line, e).into())
+        })
+        .collect()
+}
+
+/// Writes passports in the format that `parse_batch` reads.
+pub fn write_batch(passports: &[Passport]) -> String {
+    let passports: Vec<_> = passports.iter().map(|p| p.to_string()).collect();
+    passports.join("\n\n") + "\n"
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn record(s: &str) -> Record {
+        key_val_lines_to_hashmap(s.lines())
+    }
+
+    #[test]
+    fn test_day4() {
+        let schema = Schema::day4();
+        for valid in &[
+            "pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980 hcl:#623a2f",
+            "eyr:2029 ecl:blu cid:129 byr:1989 iyr:2014 pid:896056539 hcl:#a97842 hgt:165cm",
+            "hcl:#888785 hgt:164cm byr:2001 iyr:2015 cid:88 pid:545766238 ecl:hzl eyr:2022",
+            "iyr:2010 hgt:158cm hcl:#b6652a ecl:blu byr:1944 eyr:2021 pid:093154719",
+        ] {
+            assert_eq!(schema.validate(&record(valid)), vec![]);
+        }
+
+        let violations = schema.validate(&record(
+            "hgt:59cm ecl:zzz eyr:2038 hcl:74454a iyr:2023 pid:3556412378 byr:2007",
+        ));
+        let violations: Vec<_> =
+            violations.iter().map(|v| v.to_string()).collect();
+        assert_eq!(
+            violations,
+            vec![
+                "byr:2007 not in 1920-2002",
+                "iyr:2023 not in 2010-2020",
+                "eyr:2038 not in 2020-2030",
+                "hgt:59cm not in 150-193cm",
+                "hcl:74454a doesn't match ^#[0-9a-fA-F]{6}$",
+                "ecl:zzz not one of amb blu brn gry grn hzl oth",
+                "pid:3556412378 doesn't match ^[0-9]{9}$",
+            ]
+        );
+
+        let incomplete = record(
+            "hcl:#cfa07d eyr:2025 pid:166559648\niyr:2011 ecl:brn hgt:59",
+        );
+        assert_eq!(
+            schema.validate(&incomplete),
+            vec![
+                Violation::Missing("byr".to_string()),
+                Violation::Invalid {
+                    field: "hgt".to_string(),
+                    value: "59".to_string(),
+                    reason: "bad unit \"\"".to_string()
+                }
+            ]
+        );
+        assert_eq!(
+            schema.presence_only().validate(&incomplete),
+            vec![Violation::Missing("byr".to_string())]
+        );
+        assert_eq!(
+            schema.validate_presence(&incomplete),
+            vec![Violation::Missing("byr".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_parse_schema() {
+        let schema: Schema = "\
+# comment
+
+n required int 1-10
+u optional units 1-2m 3-4ft
+"
+        .parse()
+        .unwrap();
+        assert!(schema.is_valid(&record("n:10 u:3ft")));
+        assert!(schema.is_valid(&record("n:1")));
+        assert!(!schema.is_valid(&record("n:1 u:3m")));
+        assert!(!schema.is_valid(&record("u:1m")));
+
+        let schema: Schema = "t required int -10--5\nd optional units -3-3C"
+            .parse()
+            .unwrap();
+        assert!(schema.is_valid(&record("t:-5 d:-3C")));
+        assert!(schema.is_valid(&record("t:-10 d:3C")));
+        assert!(!schema.is_valid(&record("t:-11")));
+        assert!(!schema.is_valid(&record("t:5")));
+        assert!(!schema.is_valid(&record("t:-6 d:-4C")));
+
+        assert!("n required".parse::<Schema>().is_err());
+        assert!("n maybe any".parse::<Schema>().is_err());
+        assert!("n required int 1".parse::<Schema>().is_err());
+        assert!("n required int 1-2cm".parse::<Schema>().is_err());
+        assert!("n required units 1-2".parse::<Schema>().is_err());
+        assert!("n required pattern (".parse::<Schema>().is_err());
+        assert!("n required between 1 2".parse::<Schema>().is_err());
+    }
+
+    #[test]
+    fn test_passport() {
+        let input = "\
+pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980
+hcl:#623A2F
+
+eyr:2029 ecl:blu cid:129 byr:1989
+iyr:2014 pid:896056539 hcl:#a97842 hgt:165cm
+
+hgt:59cm ecl:zzz eyr:2038 hcl:74454a iyr:2023 pid:3556412378 byr:2007
+";
+        let batch = parse_batch(input);
+        assert_eq!(batch.len(), 3);
+        assert!(batch[2].is_err());
+        let passports: Vec<Passport> =
+            batch.into_iter().filter_map(Result::ok).collect();
+        assert_eq!(
+            passports[0],
+            Passport {
+                birth_year: 1980,
+                issue_year: 2012,
+                expiration_year: 2030,
+                height: Height::In(74),
+                hair_color: Rgb {
+                    r: 0x62,
+                    g: 0x3a,
+                    b: 0x2f
+                },
+                eye_color: EyeColor::Grn,
+                passport_id: "087499704".to_string(),
+                country_id: None,
+            }
+        );
+        assert_eq!(passports[1].height, Height::Cm(165));
+        assert_eq!(passports[1].country_id, Some("129".to_string()));
+
+        let written = write_batch(&passports);
+        assert_eq!(
+            written,
+            "\
+byr:1980 iyr:2012 eyr:2030 hgt:74in hcl:#623a2f ecl:grn pid:087499704
+
+byr:1989 iyr:2014 eyr:2029 hgt:165cm hcl:#a97842 ecl:blu pid:896056539 cid:129
+"
+        );
+        let reparsed: Vec<_> = parse_batch(&written)
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(reparsed, passports);
+
+        let err = "hgt:59cm ecl:zzz byr:1980"
+            .parse::<Passport>()
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("missing iyr, missing eyr, hgt:59cm"));
+    }
+
+    #[test]
+    fn test_rgb() {
+        assert_eq!(
+            "#0aFf10".parse::<Rgb>().unwrap(),
+            Rgb {
+                r: 0x0a,
+                g: 0xff,
+                b: 0x10
+            }
+        );
+        // Six bytes, but not six hex digits.
+        assert!("#aéaé".parse::<Rgb>().is_err());
+        assert!("#+f0000".parse::<Rgb>().is_err());
+        assert!("#+f00+0".parse::<Rgb>().is_err());
+        assert!("#12345".parse::<Rgb>().is_err());
+        assert!("123456".parse::<Rgb>().is_err());
+    }
+}
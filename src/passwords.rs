@@ -1,6 +1,7 @@
 use lazy_static::lazy_static;
 use regex::Regex;
 use std::error::Error;
+use std::fmt;
 use std::str::FromStr;
 
 /// Since we end up implementing multiple policies, and could end up needing more in later days, we
@@ -8,6 +9,15 @@ use std::str::FromStr;
 pub trait PasswordPolicy {
     /// Returns whether `password` satisfies the policy.
     fn check(&self, password: &str) -> bool;
+
+    /// Returns why `password` doesn't satisfy the policy, or `None` if it does.
+    fn violation(&self, password: &str) -> Option<String> {
+        if self.check(password) {
+            None
+        } else {
+            Some("doesn't satisfy the policy".to_string())
+        }
+    }
 }
 
 /// The part-1 policy.
@@ -61,6 +71,17 @@ impl PasswordPolicy for LegacyPasswordPolicy {
         let c = password.chars().filter(|c| c == &self.c).count();
         c >= self.min && c <= self.max
     }
+
+    /// ```
+    /// use aoc2020::passwords::{PasswordPolicy, LegacyPasswordPolicy};
+    /// assert_eq!(
+    ///     LegacyPasswordPolicy{min: 1, max: 3, c: 'b'}.violation("cdefg").unwrap(),
+    ///     "found 0 × 'b', allowed 1–3"
+    /// );
+    /// ```
+    fn violation(&self, password: &str) -> Option<String> {
+        count_violation(password, self.c, self.min, self.max)
+    }
 }
 
 /// The part-2 policy.
@@ -108,12 +129,458 @@ impl PasswordPolicy for NewPasswordPolicy {
     /// assert!(!NewPasswordPolicy{pos1: 2, pos2: 9, c: 'c'}.check("ccccccccc"));
     /// ```
     fn check(&self, password: &str) -> bool {
-        let check_pos =
-            |p: usize| password.chars().nth(p - 1).unwrap() == self.c;
-        let p1 = check_pos(self.pos1);
-        let p2 = check_pos(self.pos2);
+        let p1 = is_at(password, self.pos1, self.c);
+        let p2 = is_at(password, self.pos2, self.c);
         (p1 || p2) && !(p1 && p2)
     }
+
+    /// ```
+    /// use aoc2020::passwords::{PasswordPolicy, NewPasswordPolicy};
+    /// assert_eq!(
+    ///     NewPasswordPolicy{pos1: 1, pos2: 3, c: 'b'}.violation("cdefg").unwrap(),
+    ///     "neither position 1 nor 3 is 'b'"
+    /// );
+    /// assert_eq!(
+    ///     NewPasswordPolicy{pos1: 2, pos2: 9, c: 'c'}.violation("ccccccccc").unwrap(),
+    ///     "both positions 2 and 9 are 'c'"
+    /// );
+    /// assert_eq!(NewPasswordPolicy{pos1: 1, pos2: 3, c: 'a'}.violation("abcde"), None);
+    /// ```
+    fn violation(&self, password: &str) -> Option<String> {
+        let at = |pos| is_at(password, pos, self.c);
+        match (at(self.pos1), at(self.pos2)) {
+            (true, true) => Some(format!(
+                "both positions {} and {} are '{}'",
+                self.pos1, self.pos2, self.c
+            )),
+            (false, false) => Some(format!(
+                "neither position {} nor {} is '{}'",
+                self.pos1, self.pos2, self.c
+            )),
+            _ => None,
+        }
+    }
+}
+
+// Whether the 1-indexed position `pos` of `password` is `c`.
+fn is_at(password: &str, pos: usize, c: char) -> bool {
+    pos > 0 && password.chars().nth(pos - 1) == Some(c)
+}
+
+// Why `password` doesn't have between `min` and `max` of `c`, if it doesn't.
+fn count_violation(
+    password: &str,
+    c: char,
+    min: usize,
+    max: usize,
+) -> Option<String> {
+    let count = password.chars().filter(|x| *x == c).count();
+    if (min..=max).contains(&count) {
+        None
+    } else {
+        Some(format!(
+            "found {} × '{}', allowed {}–{}",
+            count, c, min, max
+        ))
+    }
+}
+
+/// A policy composed from simple checks. The text form is:
+///
+/// - `count 'c' MIN-MAX`: `c` occurs between `MIN` and `MAX` times.
+/// - `at POS 'c'`: the 1-indexed position `POS` is `c`.
+/// - `matches /REGEX/`: the password matches `REGEX`; `\/` is a literal `/`.
+/// - `minlen N`: the password has at least `N` characters.
+/// - `not P`, `P and Q`, `P or Q` and parentheses, with `not` binding
+///   tightest and `or` loosest.
+///
+/// ```
+/// use aoc2020::passwords::{Policy, PasswordPolicy};
+/// let p: Policy = "count 'a' 1-3 and not at 1 'b' or minlen 10".parse().unwrap();
+/// assert!(p.check("xax"));
+/// assert!(!p.check("bax"));
+/// assert!(p.check("bbbbbbbbbb"));
+/// assert_eq!(p.to_string(), "count 'a' 1-3 and not at 1 'b' or minlen 10");
+/// let q: Policy = "count 'a' 1-3 and (not at 1 'b' or minlen 10)".parse().unwrap();
+/// assert!(!q.check("bbbbbbbbbb"));
+/// assert_eq!(q.to_string(), "count 'a' 1-3 and (not at 1 'b' or minlen 10)");
+///
+/// let p: Policy = r"matches /^\d+\/\d+$/".parse().unwrap();
+/// assert!(p.check("12/25"));
+/// assert!(!p.check("12-25"));
+/// ```
+#[derive(Debug, Clone)]
+pub enum Policy {
+    Count { c: char, min: usize, max: usize },
+    At { pos: usize, c: char },
+    Matches(Regex),
+    MinLength(usize),
+    Not(Box<Policy>),
+    And(Box<Policy>, Box<Policy>),
+    Or(Box<Policy>, Box<Policy>),
+}
+
+/// The part-1 policy: `count 'c' MIN-MAX`.
+///
+/// ```
+/// use aoc2020::passwords::{LegacyPasswordPolicy, Policy};
+/// let p = Policy::from(LegacyPasswordPolicy{min: 1, max: 3, c: 'a'});
+/// assert_eq!(p.to_string(), "count 'a' 1-3");
+/// ```
+impl From<LegacyPasswordPolicy> for Policy {
+    fn from(p: LegacyPasswordPolicy) -> Policy {
+        Policy::Count {
+            c: p.c,
+            min: p.min,
+            max: p.max,
+        }
+    }
+}
+
+/// The part-2 policy: exactly one of the two positions is `c`.
+///
+/// ```
+/// use aoc2020::passwords::{NewPasswordPolicy, Policy};
+/// let p = Policy::from(NewPasswordPolicy{pos1: 1, pos2: 3, c: 'a'});
+/// assert_eq!(
+///     p.to_string(),
+///     "(at 1 'a' or at 3 'a') and not (at 1 'a' and at 3 'a')"
+/// );
+/// ```
+impl From<NewPasswordPolicy> for Policy {
+    fn from(p: NewPasswordPolicy) -> Policy {
+        let at = |pos| Box::new(Policy::At { pos, c: p.c });
+        Policy::And(
+            Box::new(Policy::Or(at(p.pos1), at(p.pos2))),
+            Box::new(Policy::Not(Box::new(Policy::And(
+                at(p.pos1),
+                at(p.pos2),
+            )))),
+        )
+    }
+}
+
+impl PasswordPolicy for Policy {
+    fn check(&self, password: &str) -> bool {
+        match self {
+            Policy::Count { c, min, max } => {
+                let count = password.chars().filter(|x| x == c).count();
+                (*min..=*max).contains(&count)
+            }
+            Policy::At { pos, c } => is_at(password, *pos, *c),
+            Policy::Matches(re) => re.is_match(password),
+            Policy::MinLength(len) => password.chars().count() >= *len,
+            Policy::Not(p) => !p.check(password),
+            Policy::And(p, q) => p.check(password) && q.check(password),
+            Policy::Or(p, q) => p.check(password) || q.check(password),
+        }
+    }
+
+    /// ```
+    /// use aoc2020::passwords::{Policy, PasswordPolicy};
+    /// let violation = |spec: &str, password| {
+    ///     spec.parse::<Policy>().unwrap().violation(password)
+    /// };
+    /// assert_eq!(
+    ///     violation("count 'c' 1-3", "ccccc").unwrap(),
+    ///     "found 5 × 'c', allowed 1–3"
+    /// );
+    /// assert_eq!(
+    ///     violation("at 4 'a' or minlen 5", "abc").unwrap(),
+    ///     "position 4 is past the end, and found 3 characters, need at least 5"
+    /// );
+    /// assert_eq!(
+    ///     violation("minlen 2 and not matches /b/", "abc").unwrap(),
+    ///     "must not match /b/"
+    /// );
+    /// assert_eq!(
+    ///     violation("not (at 1 'a' or minlen 9)", "abc").unwrap(),
+    ///     "must not satisfy at 1 'a' or minlen 9"
+    /// );
+    /// assert_eq!(
+    ///     violation(r"matches /^\d+\/\d+$/", "abc").unwrap(),
+    ///     r"doesn't match /^\d+\/\d+$/"
+    /// );
+    /// assert_eq!(violation("at 2 'b' and matches /^a/", "abc"), None);
+    /// ```
+    fn violation(&self, password: &str) -> Option<String> {
+        if self.check(password) {
+            return None;
+        }
+        Some(match self {
+            Policy::Count { c, min, max } => {
+                count_violation(password, *c, *min, *max)?
+            }
+            Policy::At { pos, c } => match pos
+                .checked_sub(1)
+                .and_then(|i| password.chars().nth(i))
+            {
+                Some(x) => format!("position {} is '{}', not '{}'", pos, x, c),
+                None => format!("position {} is past the end", pos),
+            },
+            Policy::Matches(_) => format!("doesn't {}", self.requirement()),
+            Policy::MinLength(len) => format!(
+                "found {} characters, need at least {}",
+                password.chars().count(),
+                len
+            ),
+            Policy::Not(p) => format!("must not {}", p.requirement()),
+            Policy::And(p, q) => {
+                p.violation(password).or_else(|| q.violation(password))?
+            }
+            Policy::Or(p, q) => format!(
+                "{}, and {}",
+                p.violation(password)?,
+                q.violation(password)?
+            ),
+        })
+    }
+}
+
+impl Policy {
+    // What the policy asks of a password, to follow "must" or "doesn't".
+    fn requirement(&self) -> String {
+        match self {
+            Policy::Count { c, min, max } => {
+                format!("have {}–{} × '{}'", min, max, c)
+            }
+            Policy::At { pos, c } => {
+                format!("have '{}' at position {}", c, pos)
+            }
+            Policy::Matches(re) => {
+                format!("match /{}/", re.as_str().replace('/', r"\/"))
+            }
+            Policy::MinLength(len) => {
+                format!("have at least {} characters", len)
+            }
+            _ => format!("satisfy {}", self),
+        }
+    }
+
+    // How tightly the policy's top level binds, for parenthesizing.
+    fn precedence(&self) -> u8 {
+        match self {
+            Policy::Or(..) => 0,
+            Policy::And(..) => 1,
+            _ => 2,
+        }
+    }
+
+    // Formats `self`, in parentheses if it binds less tightly than `min`.
+    fn fmt_operand(&self, f: &mut fmt::Formatter, min: u8) -> fmt::Result {
+        if self.precedence() < min {
+            write!(f, "({})", self)
+        } else {
+            write!(f, "{}", self)
+        }
+    }
+}
+
+impl fmt::Display for Policy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Policy::Count { c, min, max } => {
+                write!(f, "count '{}' {}-{}", c, min, max)
+            }
+            Policy::At { pos, c } => write!(f, "at {} '{}'", pos, c),
+            Policy::Matches(re) => {
+                write!(f, "matches /{}/", re.as_str().replace('/', r"\/"))
+            }
+            Policy::MinLength(len) => write!(f, "minlen {}", len),
+            Policy::Not(p) => {
+                write!(f, "not ")?;
+                p.fmt_operand(f, 2)
+            }
+            Policy::And(p, q) => {
+                p.fmt_operand(f, 1)?;
+                write!(f, " and ")?;
+                q.fmt_operand(f, 2)
+            }
+            Policy::Or(p, q) => {
+                p.fmt_operand(f, 0)?;
+                write!(f, " or ")?;
+                q.fmt_operand(f, 1)
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Word(String),
+    Char(char),
+    Regex(String),
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, Box<dyn Error>> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => (),
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            '\'' => {
+                let c = chars.next().ok_or("Unterminated character")?;
+                if chars.next() != Some('\'') {
+                    return Err(format!("Expected ' after '{}", c).into());
+                }
+                tokens.push(Token::Char(c));
+            }
+            '/' => {
+                let mut re = String::new();
+                loop {
+                    match chars.next().ok_or("Unterminated regex")? {
+                        '/' => break,
+                        '\\' if chars.peek() == Some(&'/') => {
+                            re.push(chars.next().unwrap())
+                        }
+                        c => re.push(c),
+                    }
+                }
+                tokens.push(Token::Regex(re));
+            }
+            c => {
+                let mut word = c.to_string();
+                while let Some(c) = chars.peek() {
+                    if c.is_whitespace() || "()'/".contains(*c) {
+                        break;
+                    }
+                    word.push(chars.next().unwrap());
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<&Token> {
+        self.pos += 1;
+        self.tokens.get(self.pos - 1)
+    }
+
+    fn keyword(&mut self, word: &str) -> bool {
+        if self.tokens.get(self.pos) == Some(&Token::Word(word.to_string())) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn number(&mut self) -> Result<usize, Box<dyn Error>> {
+        match self.next() {
+            Some(Token::Word(w)) => Ok(w.parse()?),
+            t => Err(format!("Expected number, got {:?}", t).into()),
+        }
+    }
+
+    fn character(&mut self) -> Result<char, Box<dyn Error>> {
+        match self.next() {
+            Some(Token::Char(c)) => Ok(*c),
+            t => Err(format!("Expected character, got {:?}", t).into()),
+        }
+    }
+
+    fn or(&mut self) -> Result<Policy, Box<dyn Error>> {
+        let mut p = self.and()?;
+        while self.keyword("or") {
+            p = Policy::Or(Box::new(p), Box::new(self.and()?));
+        }
+        Ok(p)
+    }
+
+    fn and(&mut self) -> Result<Policy, Box<dyn Error>> {
+        let mut p = self.unary()?;
+        while self.keyword("and") {
+            p = Policy::And(Box::new(p), Box::new(self.unary()?));
+        }
+        Ok(p)
+    }
+
+    fn unary(&mut self) -> Result<Policy, Box<dyn Error>> {
+        match self.next() {
+            Some(Token::Open) => {
+                let p = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(p),
+                    t => Err(format!("Expected ), got {:?}", t).into()),
+                }
+            }
+            Some(Token::Word(w)) => match w.as_str() {
+                "not" => Ok(Policy::Not(Box::new(self.unary()?))),
+                "count" => {
+                    let c = self.character()?;
+                    let range = match self.next() {
+                        Some(Token::Word(w)) => w.clone(),
+                        t => {
+                            return Err(
+                                format!("Expected range, got {:?}", t).into()
+                            )
+                        }
+                    };
+                    let mut bounds = range.splitn(2, '-');
+                    let min = bounds.next().unwrap().parse()?;
+                    let max = bounds
+                        .next()
+                        .ok_or_else(|| format!("Bad range {}", range))?
+                        .parse()?;
+                    Ok(Policy::Count { c, min, max })
+                }
+                "at" => {
+                    let pos = self.number()?;
+                    if pos == 0 {
+                        return Err("Positions start at 1".into());
+                    }
+                    Ok(Policy::At {
+                        pos,
+                        c: self.character()?,
+                    })
+                }
+                "matches" => match self.next() {
+                    Some(Token::Regex(re)) => {
+                        Ok(Policy::Matches(Regex::new(re)?))
+                    }
+                    t => Err(format!("Expected /regex/, got {:?}", t).into()),
+                },
+                "minlen" => Ok(Policy::MinLength(self.number()?)),
+                w => Err(format!("Unknown check {}", w).into()),
+            },
+            t => Err(format!("Expected check, got {:?}", t).into()),
+        }
+    }
+}
+
+impl FromStr for Policy {
+    type Err = Box<dyn Error>;
+    /// ```
+    /// use aoc2020::passwords::Policy;
+    /// assert!("count 'a' 1".parse::<Policy>().is_err());
+    /// assert!("at 0 'a'".parse::<Policy>().is_err());
+    /// assert!("(minlen 3".parse::<Policy>().is_err());
+    /// assert!("minlen 3 minlen 4".parse::<Policy>().is_err());
+    /// assert!("matches /(/".parse::<Policy>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            pos: 0,
+        };
+        let p = parser.or()?;
+        if let Some(t) = parser.tokens.get(parser.pos) {
+            return Err(format!("Unexpected {:?}", t).into());
+        }
+        Ok(p)
+    }
 }
 
 /// Parse a line of input into a `PasswordPolicy` and password string.
@@ -122,6 +589,13 @@ impl PasswordPolicy for NewPasswordPolicy {
 /// use aoc2020::passwords::{LegacyPasswordPolicy, NewPasswordPolicy, parse_line};
 /// assert_eq!(parse_line("1-3 a: abcde").unwrap(), (LegacyPasswordPolicy{min:1, max:3, c: 'a'}, "abcde"));
 /// assert_eq!(parse_line("1-3 a: abcde").unwrap(), (NewPasswordPolicy{pos1:1, pos2:3, c: 'a'}, "abcde"));
+/// // Passwords may contain the separator.
+/// assert_eq!(parse_line("1-3 a: ab: c").unwrap(), (LegacyPasswordPolicy{min:1, max:3, c: 'a'}, "ab: c"));
+///
+/// // So may regexes.
+/// use aoc2020::passwords::Policy;
+/// let (policy, password) = parse_line::<Policy>(r"matches /a: \/b/: x: a/b").unwrap();
+/// assert_eq!((policy.to_string().as_str(), password), (r"matches /a: \/b/", "x: a/b"));
 /// ```
 pub fn parse_line<P>(s: &str) -> Result<(P, &str), Box<dyn Error>>
 where
@@ -132,10 +606,31 @@ where
     // resolve it,but that doesn't seem right either.
     P: FromStr<Err = Box<dyn Error>> + PasswordPolicy,
 {
-    let mut parts = s.splitn(2, ": ");
-    let policy = parts.next().ok_or("Missing policy")?.parse::<P>()?;
-    let password = parts.next().ok_or("Missing password")?;
-    Ok((policy, password))
+    let (policy, password) = split_line(s).ok_or("Missing password")?;
+    Ok((policy.parse::<P>()?, password))
+}
+
+// Splits `s` at the first ": ", skipping any inside a `Policy` regex, since
+// only the password may contain one otherwise.
+fn split_line(s: &str) -> Option<(&str, &str)> {
+    let mut in_regex = false;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        if in_regex {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '/' {
+                in_regex = false;
+            }
+        } else if c == '/' {
+            in_regex = true;
+        } else if s[i..].starts_with(": ") {
+            return Some((&s[..i], &s[i + 2..]));
+        }
+    }
+    None
 }
 
 /// Parse a line of input, containing a policy and password, and return whether the password
@@ -146,6 +641,10 @@ where
 /// assert!(check_line::<LegacyPasswordPolicy>("1-3 a: abcde").unwrap());
 /// assert!(!check_line::<LegacyPasswordPolicy>("1-3 b: cdefg").unwrap());
 /// assert!(check_line::<LegacyPasswordPolicy>("2-9 c: ccccccccc").unwrap());
+///
+/// use aoc2020::passwords::Policy;
+/// assert!(check_line::<Policy>("count 'a' 1-3 and minlen 5: abcde").unwrap());
+/// assert!(!check_line::<Policy>("at 1 'a' and at 2 'a': abcde").unwrap());
 /// ```
 /// XXX: as above re would be nice to be more permissive about `FromStr`'s `Err` type.
 ///
@@ -161,3 +660,132 @@ where
     let (policy, password) = parse_line::<P>(line)?;
     Ok(policy.check(password))
 }
+
+/// A line that failed its policy.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Violation {
+    /// 1-indexed
+    pub line: usize,
+    /// As written on the line.
+    pub policy: String,
+    pub password: String,
+    pub reason: String,
+}
+
+/// Checks every line, returning the ones that fail their policy.
+///
+/// ```
+/// use aoc2020::passwords::{audit, LegacyPasswordPolicy, Violation};
+/// let lines = ["1-3 a: abcde", "1-3 b: cdefg", "2-9 c: ccccccccc"];
+/// assert_eq!(
+///     audit::<LegacyPasswordPolicy, _>(&lines).unwrap(),
+///     vec![Violation {
+///         line: 2,
+///         policy: "1-3 b".to_string(),
+///         password: "cdefg".to_string(),
+///         reason: "found 0 × 'b', allowed 1–3".to_string(),
+///     }]
+/// );
+/// ```
+pub fn audit<P, S>(
+    lines: impl IntoIterator<Item = S>,
+) -> Result<Vec<Violation>, Box<dyn Error>>
+where
+    P: FromStr<Err = Box<dyn Error>> + PasswordPolicy,
+    S: AsRef<str>,
+{
+    let mut violations = Vec::new();
+    for (i, line) in lines.into_iter().enumerate() {
+        let line = line.as_ref();
+        let (policy, password) = parse_line::<P>(line)
+            .map_err(|e| format!("Line {}: {}", i + 1, e))?;
+        if let Some(reason) = policy.violation(password) {
+            violations.push(Violation {
+                line: i + 1,
+                policy: line[..line.len() - password.len() - 2].to_string(),
+                password: password.to_string(),
+                reason,
+            });
+        }
+    }
+    Ok(violations)
+}
+
+/// One line per violation.
+///
+/// ```
+/// use aoc2020::passwords::{audit, report_text, NewPasswordPolicy};
+/// let lines = ["1-3 a: abcde", "1-3 b: cdefg"];
+/// let violations = audit::<NewPasswordPolicy, _>(&lines).unwrap();
+/// assert_eq!(
+///     report_text(&violations),
+///     "line 2: \"cdefg\" fails \"1-3 b\": neither position 1 nor 3 is 'b'\n"
+/// );
+/// ```
+pub fn report_text(violations: &[Violation]) -> String {
+    violations
+        .iter()
+        .map(|v| {
+            format!(
+                "line {}: {:?} fails {:?}: {}\n",
+                v.line, v.password, v.policy, v.reason
+            )
+        })
+        .collect()
+}
+
+/// A JSON array with an object per violation.
+///
+/// ```
+/// use aoc2020::passwords::{audit, report_json, Policy};
+/// let lines = ["minlen 4: abc"];
+/// let violations = audit::<Policy, _>(&lines).unwrap();
+/// assert_eq!(
+///     report_json(&violations),
+///     r#"[{"line":1,"password":"abc","policy":"minlen 4","reason":"found 3 characters, need at least 4"}]"#
+/// );
+/// ```
+pub fn report_json(violations: &[Violation]) -> String {
+    serde_json::Value::Array(
+        violations
+            .iter()
+            .map(|v| {
+                serde_json::json!({
+                    "line": v.line,
+                    "policy": v.policy,
+                    "password": v.password,
+                    "reason": v.reason,
+                })
+            })
+            .collect(),
+    )
+    .to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // The built-in policies are checked directly, for speed; they must agree
+    // with their `Policy` forms.
+    #[test]
+    fn test_policy_forms_agree() {
+        for line in include_str!("../inputs/day2").lines() {
+            let (legacy, password) =
+                parse_line::<LegacyPasswordPolicy>(line).unwrap();
+            let (new, _) = parse_line::<NewPasswordPolicy>(line).unwrap();
+            assert_eq!(
+                legacy.check(password),
+                Policy::from(legacy.clone()).check(password),
+                "{}",
+                line
+            );
+            assert_eq!(
+                new.check(password),
+                Policy::from(new.clone()).check(password),
+                "{}",
+                line
+            );
+        }
+    }
+}
@@ -0,0 +1,34 @@
+//! RGB images of grids of cells, shared by the exporters of days 11 and 20.
+
+use std::io::{self, Write};
+
+pub type Rgb = [u8; 3];
+
+/// RGB pixel data for a `height` x `width` grid of cells, with each cell a
+/// `scale` x `scale` square of `color((row, col))`.
+pub fn scaled_pixels<F: Fn((usize, usize)) -> Rgb>(
+    height: usize,
+    width: usize,
+    scale: usize,
+    color: F,
+) -> Vec<u8> {
+    let mut data = Vec::with_capacity(height * width * scale * scale * 3);
+    for y in 0..height * scale {
+        for x in 0..width * scale {
+            data.extend_from_slice(&color((y / scale, x / scale)));
+        }
+    }
+    data
+}
+
+/// Writes pixel `data` as a binary PPM image.
+pub fn write_ppm<W: Write>(
+    mut w: W,
+    height: usize,
+    width: usize,
+    data: &[u8],
+) -> io::Result<()> {
+    debug_assert_eq!(data.len(), height * width * 3);
+    write!(w, "P6\n{} {}\n255\n", width, height)?;
+    w.write_all(data)
+}
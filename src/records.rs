@@ -0,0 +1,218 @@
+//! Splitting input into records separated by blank lines, as used by many
+//! days' inputs. Lines may end in `\n` or `\r\n`, and lines containing only
+//! whitespace count as blank.
+
+use std::error::Error;
+use std::io::BufRead;
+
+/// A record, and the 1-indexed line it starts on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record<S> {
+    pub line: usize,
+    pub text: S,
+}
+
+impl<S: AsRef<str>> Record<S> {
+    pub fn lines(&self) -> std::str::Lines<'_> {
+        self.text.as_ref().lines()
+    }
+}
+
+/// Iterator over the records of an in-memory string; see `records`.
+pub struct Records<'a> {
+    rest: &'a str,
+    // Line number of the start of `rest`.
+    line: usize,
+}
+
+// Splits off the first line, without its `\n`.
+fn split_line(s: &str) -> (&str, &str) {
+    match s.find('\n') {
+        Some(i) => (&s[..i], &s[i + 1..]),
+        None => (s, ""),
+    }
+}
+
+fn is_blank(line: &str) -> bool {
+    line.trim().is_empty()
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = Record<&'a str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Skip blank lines before the record.
+        loop {
+            if self.rest.is_empty() {
+                return None;
+            }
+            let (line, rest) = split_line(self.rest);
+            if !is_blank(line) {
+                break;
+            }
+            self.rest = rest;
+            self.line += 1;
+        }
+
+        let start = self.rest;
+        let start_line = self.line;
+        let mut len = 0;
+        while !self.rest.is_empty() {
+            let (line, rest) = split_line(self.rest);
+            if is_blank(line) {
+                break;
+            }
+            len = start.len() - self.rest.len()
+                + line.strip_suffix('\r').unwrap_or(line).len();
+            self.rest = rest;
+            self.line += 1;
+        }
+        Some(Record {
+            line: start_line,
+            text: &start[..len],
+        })
+    }
+}
+
+/// Splits `input` into records, borrowing from it. Each record's text runs
+/// from the start of its first line to the end of its last, excluding the
+/// final line ending.
+pub fn records(input: &str) -> Records<'_> {
+    Records {
+        rest: input,
+        line: 1,
+    }
+}
+
+/// Iterator over the records of a reader; see `read_records`.
+pub struct ReadRecords<R: BufRead> {
+    reader: R,
+    line: usize,
+    buf: String,
+}
+
+impl<R: BufRead> Iterator for ReadRecords<R> {
+    type Item = Result<Record<String>, Box<dyn Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut record: Option<Record<String>> = None;
+        loop {
+            self.buf.clear();
+            match self.reader.read_line(&mut self.buf) {
+                Err(e) => return Some(Err(e.into())),
+                Ok(0) => return record.map(Ok),
+                Ok(_) => (),
+            }
+            self.line += 1;
+            let line = self.buf.trim_end_matches(&['\n', '\r'][..]);
+            if is_blank(line) {
+                if record.is_some() {
+                    return record.map(Ok);
+                }
+                continue;
+            }
+            match &mut record {
+                None => {
+                    record = Some(Record {
+                        line: self.line,
+                        text: line.to_string(),
+                    })
+                }
+                Some(record) => {
+                    record.text.push('\n');
+                    record.text.push_str(line);
+                }
+            }
+        }
+    }
+}
+
+/// Like `records`, but reading from `reader`. Each record is a single
+/// `String`, with lines joined by `\n`.
+pub fn read_records<R: BufRead>(reader: R) -> ReadRecords<R> {
+    ReadRecords {
+        reader,
+        line: 0,
+        buf: String::new(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_records() {
+        let input = "line1\nline2\n\nline3\nline4";
+        let result: Vec<_> = records(input).collect();
+        assert_eq!(
+            result,
+            vec![
+                Record {
+                    line: 1,
+                    text: "line1\nline2"
+                },
+                Record {
+                    line: 4,
+                    text: "line3\nline4"
+                },
+            ]
+        );
+
+        let result: Vec<_> = read_records(Cursor::new(input))
+            .map(|x| x.unwrap())
+            .collect();
+        assert_eq!(
+            result,
+            vec![
+                Record {
+                    line: 1,
+                    text: "line1\nline2".to_string()
+                },
+                Record {
+                    line: 4,
+                    text: "line3\nline4".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_messy_records() {
+        let input = "\r\n \na\r\nb \r\n\t\r\n\r\n\nc\r\n  \n";
+        let result: Vec<_> = records(input).collect();
+        assert_eq!(
+            result,
+            vec![
+                Record {
+                    line: 3,
+                    text: "a\r\nb "
+                },
+                Record { line: 8, text: "c" },
+            ]
+        );
+        assert_eq!(result[0].lines().collect::<Vec<_>>(), vec!["a", "b "]);
+
+        let result: Vec<_> = read_records(Cursor::new(input))
+            .map(|x| x.unwrap())
+            .collect();
+        assert_eq!(
+            result,
+            vec![
+                Record {
+                    line: 3,
+                    text: "a\nb ".to_string()
+                },
+                Record {
+                    line: 8,
+                    text: "c".to_string()
+                },
+            ]
+        );
+
+        assert_eq!(records("").count(), 0);
+        assert_eq!(records("\n \n").count(), 0);
+        assert_eq!(read_records(Cursor::new("\n \n")).count(), 0);
+    }
+}
@@ -1,11 +1,20 @@
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+use crate::automaton::{Automaton, Dense, Rule, Topology};
+use crate::raster::{self, Rgb};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 pub enum Position {
     Floor,
     Empty,
     Occupied,
 }
 
-#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
 pub struct Grid {
     positions: Vec<Position>,
     rows: usize,
@@ -23,10 +32,7 @@ impl Grid {
     }
 
     pub fn get(&self, col: isize, row: isize) -> Option<Position> {
-        match self.idx(col, row) {
-            Some(i) => Some(self.positions[i]),
-            None => None,
-        }
+        self.idx(col, row).map(|i| self.positions[i])
     }
 
     pub fn set(&mut self, col: isize, row: isize, pos: Position) {
@@ -40,80 +46,56 @@ impl Grid {
             (-1,0),        (1,0),
             (-1,-1),(0,-1),(1,-1)];
 
-    pub fn adjacent_occupied(&self, col: isize, row: isize) -> usize {
-        Grid::DIRECTIONS
-            .iter()
-            .map(|(dcol, drow)| self.get(col + dcol, row + drow))
-            .filter(|p| p == &Some(Position::Occupied))
-            .count()
-    }
-
-    pub fn visible_occupied(&self, col: isize, row: isize) -> usize {
-        let mut count = 0;
+    // Pushes the seats that the seat at (col, row) pays attention to.
+    fn visible_seats(
+        &self,
+        col: isize,
+        row: isize,
+        policy: &Policy,
+        out: &mut Vec<(isize, isize)>,
+    ) {
+        let max_distance = match (policy.visibility, policy.max_distance) {
+            (Visibility::Adjacent, _) => 1,
+            (Visibility::LineOfSight, Some(max)) => max as isize,
+            (Visibility::LineOfSight, None) => isize::MAX,
+        };
         for (dcol, drow) in Grid::DIRECTIONS.iter() {
             let mut distance = 1;
-            loop {
-                let col = col + *dcol as isize * distance;
-                let row = row + *drow as isize * distance;
+            while distance <= max_distance {
+                let col = col + *dcol * distance;
+                let row = row + *drow * distance;
                 match self.get(col, row) {
-                    // Off the chart; done with this direction.
                     None => break,
-                    // Need to keep looking further.
                     Some(Position::Floor) => (),
-                    // Empty seat; done with this direction.
-                    Some(Position::Empty) => break,
-                    // Occupied seat; increment and done with this direction.
-                    Some(Position::Occupied) => {
-                        count += 1;
+                    Some(_) => {
+                        out.push((col, row));
                         break;
                     }
-                };
+                }
                 distance += 1;
             }
         }
-        count
     }
 
-    // Optionally takes storage for `next` (which must match the shape of `self`), allowing us to
-    // avoid hitting the allocator. Returns the next state.
-    pub fn step<F: Fn(&Grid, isize, isize) -> usize>(
-        &self,
-        next: Option<Grid>,
-        count_neighbor_fn: F,
-        neighbor_limit: usize,
-    ) -> Grid {
-        let mut next = match next {
-            Some(next) => next,
-            None => self.clone(),
-        };
-        debug_assert_eq!(self.cols, next.cols);
-        debug_assert_eq!(self.rows, next.rows);
-
-        for row in 0..self.rows {
-            for col in 0..self.cols {
-                let row = row as isize;
-                let col = col as isize;
-
-                let pos = self.get(col, row).unwrap();
-                if pos == Position::Floor {
-                    next.set(col, row, pos);
-                    continue;
-                }
-                let occupied_neighbor_count = count_neighbor_fn(self, col, row);
-                let next_pos = if pos == Position::Occupied
-                    && occupied_neighbor_count >= neighbor_limit
-                {
-                    Position::Empty
-                } else if pos == Position::Empty && occupied_neighbor_count == 0
-                {
-                    Position::Occupied
-                } else {
-                    pos
-                };
-                next.set(col, row, next_pos);
-            }
+    // This grid's floor, with seats occupied according to `alive`, in the
+    // order of `Seats::cells`.
+    fn with_state(&self, alive: &[bool]) -> Grid {
+        let mut alive = alive.iter();
+        let positions = self
+            .positions
+            .iter()
+            .map(|pos| match pos {
+                Position::Floor => Position::Floor,
+                _ if *alive.next().unwrap() => Position::Occupied,
+                _ => Position::Empty,
+            })
+            .collect();
+        debug_assert!(alive.next().is_none());
+        Grid {
+            positions,
+            rows: self.rows,
+            cols: self.cols,
         }
-        next
     }
 
     pub fn occupied(&self) -> usize {
@@ -129,6 +111,255 @@ impl Grid {
         }
         count
     }
+
+    const FLOOR: Rgb = [0x60, 0x60, 0x60];
+    const EMPTY: Rgb = [0x20, 0x80, 0x20];
+    const OCCUPIED: Rgb = [0xe0, 0x30, 0x30];
+
+    /// Writes a binary PPM image with each position as a `scale` x `scale`
+    /// square.
+    pub fn write_ppm<W: Write>(
+        &self,
+        scale: usize,
+        w: W,
+    ) -> std::io::Result<()> {
+        let data =
+            raster::scaled_pixels(self.rows, self.cols, scale, |(row, col)| {
+                match self.positions[row * self.cols + col] {
+                    Position::Floor => Grid::FLOOR,
+                    Position::Empty => Grid::EMPTY,
+                    Position::Occupied => Grid::OCCUPIED,
+                }
+            });
+        raster::write_ppm(w, self.rows * scale, self.cols * scale, &data)
+    }
+}
+
+/// The format that `parse` reads, one line per row.
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for row in self.positions.chunks(self.cols) {
+            for pos in row {
+                let c = match pos {
+                    Position::Floor => '.',
+                    Position::Empty => 'L',
+                    Position::Occupied => '#',
+                };
+                write!(f, "{}", c)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Which other seats a passenger pays attention to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Visibility {
+    /// Seats in the 8 surrounding positions.
+    Adjacent,
+    /// The first seat in each of the 8 directions, looking past floor.
+    LineOfSight,
+}
+
+/// How passengers choose and leave seats.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Policy {
+    pub visibility: Visibility,
+    /// How far to look in each direction with `LineOfSight`; unlimited if
+    /// `None`.
+    pub max_distance: Option<usize>,
+    /// An empty seat is taken when at most this many visible seats are
+    /// occupied.
+    pub birth: usize,
+    /// An occupied seat is vacated when at least this many visible seats are
+    /// occupied.
+    pub death: usize,
+}
+
+impl Policy {
+    pub const PART1: Policy = Policy {
+        visibility: Visibility::Adjacent,
+        max_distance: None,
+        birth: 0,
+        death: 4,
+    };
+
+    pub const PART2: Policy = Policy {
+        visibility: Visibility::LineOfSight,
+        max_distance: None,
+        birth: 0,
+        death: 5,
+    };
+
+    /// The policy as a birth/survival rule.
+    pub fn rule(&self) -> Rule {
+        Rule::new(
+            &(0..=self.birth).collect::<Vec<_>>(),
+            &(0..self.death).collect::<Vec<_>>(),
+        )
+    }
+}
+
+/// Where repeatedly applying a policy leads.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Outcome {
+    /// Nothing changes after `generation`.
+    Stable { generation: usize, occupied: usize },
+    /// The grid at generation `start` recurs every `period` generations.
+    Cycle { start: usize, period: usize },
+}
+
+/// The seats of a `Grid` as an automaton topology, with cells identified by
+/// (col, row). Floor isn't part of the topology.
+pub struct Seats<'a> {
+    pub grid: &'a Grid,
+    pub policy: Policy,
+}
+
+impl<'a> Topology for Seats<'a> {
+    type Cell = (isize, isize);
+
+    fn neighbors(&self, cell: &(isize, isize), out: &mut Vec<(isize, isize)>) {
+        self.grid.visible_seats(cell.0, cell.1, &self.policy, out);
+    }
+
+    fn cells(&self) -> Option<Vec<(isize, isize)>> {
+        let grid = self.grid;
+        Some(
+            (0..grid.rows as isize)
+                .flat_map(|row| {
+                    (0..grid.cols as isize).map(move |col| (col, row))
+                })
+                .filter(|(col, row)| {
+                    grid.get(*col, *row) != Some(Position::Floor)
+                })
+                .collect(),
+        )
+    }
+}
+
+/// A grid stepped under a policy. Who can see whom is worked out once, in
+/// `new`, so each step only visits seats and the seats they can see.
+pub struct Simulation<'a> {
+    zero: &'a Grid,
+    automaton: Dense<Seats<'a>>,
+}
+
+impl<'a> Simulation<'a> {
+    pub fn new(zero: &'a Grid, policy: &Policy) -> Simulation<'a> {
+        let seats = Seats {
+            grid: zero,
+            policy: *policy,
+        };
+        let occupied: Vec<_> = seats
+            .cells()
+            .unwrap()
+            .into_iter()
+            .filter(|(col, row)| {
+                zero.get(*col, *row) == Some(Position::Occupied)
+            })
+            .collect();
+        Simulation {
+            zero,
+            automaton: Dense::new(seats, policy.rule(), occupied).unwrap(),
+        }
+    }
+
+    /// Advances one generation. Returns whether anything changed.
+    pub fn step(&mut self) -> bool {
+        self.automaton.step()
+    }
+
+    /// The current grid.
+    pub fn grid(&self) -> Grid {
+        self.zero.with_state(self.automaton.state())
+    }
+
+    /// How many seats are occupied.
+    pub fn occupied(&self) -> usize {
+        self.automaton.population()
+    }
+}
+
+// Applies `policy` to `zero` until a grid repeats, passing each distinct
+// state to `frame`: up to the stable one, or up to just before the first
+// repeat of a cycle.
+fn simulate<F: FnMut(&[bool])>(
+    zero: &Grid,
+    policy: &Policy,
+    mut frame: F,
+) -> Outcome {
+    let mut simulation = Simulation::new(zero, policy);
+    let mut seen = HashMap::new();
+    for generation in 0.. {
+        let state = simulation.automaton.state().to_vec();
+        if let Some(start) = seen.get(&state) {
+            return Outcome::Cycle {
+                start: *start,
+                period: generation - start,
+            };
+        }
+        frame(&state);
+        if !simulation.step() {
+            return Outcome::Stable {
+                generation,
+                occupied: simulation.occupied(),
+            };
+        }
+        seen.insert(state, generation);
+    }
+    unreachable!()
+}
+
+/// Applies `policy` to `zero` until a grid repeats.
+pub fn run(zero: &Grid, policy: &Policy) -> Outcome {
+    simulate(zero, policy, |_| ())
+}
+
+/// Every distinct grid from `zero` on: up to the stable one, or up to just
+/// before the first repeat of a cycle.
+pub fn generations(zero: &Grid, policy: &Policy) -> Vec<Grid> {
+    let mut res = Vec::new();
+    simulate(zero, policy, |state| res.push(zero.with_state(state)));
+    res
+}
+
+/// Writes each frame as `generation-NNNN.ppm` in `dir`, which must exist.
+pub fn write_ppm_frames(
+    frames: &[Grid],
+    scale: usize,
+    dir: &Path,
+) -> std::io::Result<()> {
+    for (generation, grid) in frames.iter().enumerate() {
+        let path = dir.join(format!("generation-{:04}.ppm", generation));
+        let mut w = BufWriter::new(File::create(path)?);
+        grid.write_ppm(scale, &mut w)?;
+        w.flush()?;
+    }
+    Ok(())
+}
+
+/// Writes all frames as text, each after a "Generation N:" header line and
+/// followed by a blank line.
+pub fn write_log<W: Write>(frames: &[Grid], mut w: W) -> std::io::Result<()> {
+    for (generation, grid) in frames.iter().enumerate() {
+        writeln!(w, "Generation {}:\n{}", generation, grid)?;
+    }
+    Ok(())
+}
+
+/// Runs the seating automaton on `zero` until it stabilizes; returns the
+/// number of occupied seats. Fails if it oscillates instead.
+pub fn settle(zero: &Grid, policy: &Policy) -> Result<usize, Box<dyn Error>> {
+    match run(zero, policy) {
+        Outcome::Stable { occupied, .. } => Ok(occupied),
+        Outcome::Cycle { start, period } => Err(format!(
+            "Seating oscillates with period {} from generation {}",
+            period, start
+        )
+        .into()),
+    }
 }
 
 pub fn parse(input: &str) -> Grid {
@@ -157,26 +388,12 @@ pub fn parse(input: &str) -> Grid {
     }
 }
 
-pub fn part1(zero: &Grid) -> usize {
-    let mut prev = zero.clone();
-    let mut curr = zero.step(None, Grid::adjacent_occupied, 4);
-    while curr != prev {
-        let next = curr.step(Some(prev), Grid::adjacent_occupied, 4);
-        prev = curr;
-        curr = next;
-    }
-    curr.occupied()
+pub fn part1(zero: &Grid) -> Result<usize, Box<dyn Error>> {
+    settle(zero, &Policy::PART1)
 }
 
-pub fn part2(zero: &Grid) -> usize {
-    let mut prev = zero.clone();
-    let mut curr = zero.step(None, Grid::visible_occupied, 5);
-    while curr != prev {
-        let next = curr.step(Some(prev), Grid::visible_occupied, 5);
-        prev = curr;
-        curr = next;
-    }
-    curr.occupied()
+pub fn part2(zero: &Grid) -> Result<usize, Box<dyn Error>> {
+    settle(zero, &Policy::PART2)
 }
 
 #[cfg(test)]
@@ -224,9 +441,19 @@ L.L.L..L..
 #.LLLLLL.L
 #.#LLLL.##",
         );
-        assert_eq!(zero.step(None, Grid::adjacent_occupied, 4), one);
-        assert_eq!(one.step(None, Grid::adjacent_occupied, 4), two);
-        assert_eq!(part1(&zero), 37);
+        let seats = Seats {
+            grid: &zero,
+            policy: Policy::PART1,
+        };
+        assert_eq!(seats.cells().unwrap().len(), 71);
+        assert_eq!(parse(&zero.to_string()), zero);
+        assert_eq!(parse(&two.to_string()), two);
+        let mut simulation = Simulation::new(&zero, &Policy::PART1);
+        assert!(simulation.step());
+        assert_eq!(simulation.grid(), one);
+        assert!(simulation.step());
+        assert_eq!(simulation.grid(), two);
+        assert_eq!(part1(&zero).unwrap(), 37);
 
         let two = parse(
             "\
@@ -242,8 +469,100 @@ LLLLLLLLL#
 #.LLLLL.L#
 ",
         );
-        assert_eq!(one.visible_occupied(0, 0), 3);
-        assert_eq!(one.step(None, Grid::visible_occupied, 5), two);
-        assert_eq!(part2(&zero), 26);
+        let mut visible = Vec::new();
+        one.visible_seats(0, 0, &Policy::PART2, &mut visible);
+        assert_eq!(visible, vec![(0, 1), (1, 1), (2, 0)]);
+        let mut simulation = Simulation::new(&one, &Policy::PART2);
+        assert!(simulation.step());
+        assert_eq!(simulation.grid(), two);
+        assert_eq!(simulation.occupied(), two.occupied());
+        assert_eq!(part2(&zero).unwrap(), 26);
+        assert_eq!(
+            run(&zero, &Policy::PART2),
+            Outcome::Stable {
+                generation: 6,
+                occupied: 26
+            }
+        );
+
+        // Looking only one seat away is the same as looking at adjacent seats.
+        let short_sighted = Policy {
+            max_distance: Some(1),
+            ..Policy::PART2
+        };
+        assert_eq!(
+            settle(
+                &zero,
+                &Policy {
+                    death: 4,
+                    ..short_sighted
+                }
+            )
+            .unwrap(),
+            37
+        );
+    }
+
+    #[test]
+    fn test_cycle() {
+        // Everyone sits down, then everyone gets up because of their neighbor.
+        let grid = parse("L.L\nLLL\n");
+        let policy = Policy {
+            death: 1,
+            ..Policy::PART1
+        };
+        assert_eq!(
+            run(&grid, &policy),
+            Outcome::Cycle {
+                start: 0,
+                period: 2
+            }
+        );
+        assert_eq!(
+            run(&parse("##L\n"), &policy),
+            Outcome::Cycle {
+                start: 1,
+                period: 2
+            }
+        );
+        assert!(settle(&grid, &policy).is_err());
+    }
+
+    #[test]
+    fn test_export() {
+        let zero = parse("L.L\nLLL\n");
+        assert_eq!(zero.to_string(), "L.L\nLLL\n");
+
+        let frames = generations(&zero, &Policy::PART1);
+        assert_eq!(frames.len(), 3);
+        let mut log = Vec::new();
+        write_log(&frames, &mut log).unwrap();
+        assert_eq!(
+            String::from_utf8(log).unwrap(),
+            "Generation 0:\nL.L\nLLL\n\n\
+             Generation 1:\n#.#\n###\n\n\
+             Generation 2:\n#.#\n#L#\n\n"
+        );
+
+        // A cycle stops before repeating.
+        let policy = Policy {
+            death: 1,
+            ..Policy::PART1
+        };
+        assert_eq!(generations(&parse("##L\n"), &policy).len(), 3);
+
+        let dir = std::env::temp_dir()
+            .join(format!("seating-frames-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        write_ppm_frames(&frames, 2, &dir).unwrap();
+        let ppm = std::fs::read(dir.join("generation-0002.ppm")).unwrap();
+        let header = b"P6\n6 4\n255\n";
+        assert!(ppm.starts_with(header));
+        assert_eq!(ppm.len(), header.len() + 6 * 4 * 3);
+        assert_eq!(ppm[header.len()..header.len() + 3], Grid::OCCUPIED);
+        assert_eq!(ppm[header.len() + 6..header.len() + 9], Grid::FLOOR);
+        let row3 = header.len() + 2 * 6 * 3;
+        assert_eq!(ppm[row3 + 6..row3 + 9], Grid::EMPTY);
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -1,10 +1,39 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
 use std::error::Error;
 use std::str::FromStr;
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
 pub enum Cell {
+    /// `.`
     Empty,
+    /// `#`
     Tree,
+    /// `~`
+    Snow,
+    /// `=`
+    Ice,
+    /// `^`
+    Rock,
+}
+
+impl Cell {
+    pub const ALL: [Cell; 5] =
+        [Cell::Empty, Cell::Tree, Cell::Snow, Cell::Ice, Cell::Rock];
+
+    pub fn symbol(&self) -> char {
+        match self {
+            Cell::Empty => '.',
+            Cell::Tree => '#',
+            Cell::Snow => '~',
+            Cell::Ice => '=',
+            Cell::Rock => '^',
+        }
+    }
+
+    pub fn from_symbol(c: char) -> Option<Cell> {
+        Cell::ALL.iter().copied().find(|cell| cell.symbol() == c)
+    }
 }
 
 #[derive(Debug)]
@@ -36,11 +65,10 @@ impl FromStr for Map {
                 .into());
             }
             for c in line.chars() {
-                cells.push(match c {
-                    '.' => Cell::Empty,
-                    '#' => Cell::Tree,
-                    _ => return Err("Bad cell".into()),
-                });
+                cells.push(
+                    Cell::from_symbol(c)
+                        .ok_or_else(|| format!("Bad cell {:?}", c))?,
+                );
             }
         }
         Ok(Map { width, cells })
@@ -78,7 +106,7 @@ impl Map {
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct Angle {
     pub right: usize,
     pub down: usize,
@@ -116,3 +144,256 @@ pub fn trees_for_angle(map: &Map, angle: Angle) -> usize {
     }
     count
 }
+
+/// Tree counts for each of `angles`, in one pass down the map.
+/// ```
+/// use aoc2020::toboggan as t;
+/// let m : t::Map = "..#\n\
+///                   #.#\n\
+///                   .##".parse().unwrap();
+/// let angles = [t::Angle{right: 1, down: 1}, t::Angle{right: 0, down: 2}];
+/// assert_eq!(t::trees_for_angles(&m, &angles), vec![1, 0]);
+/// ```
+pub fn trees_for_angles(map: &Map, angles: &[Angle]) -> Vec<usize> {
+    let mut counts = vec![0; angles.len()];
+    for row in 1..map.height() {
+        for (angle, count) in angles.iter().zip(counts.iter_mut()) {
+            if angle.down == 0 || row % angle.down != 0 {
+                continue;
+            }
+            let col = row / angle.down * angle.right;
+            if map.get(row, col).unwrap() == &Cell::Tree {
+                *count += 1;
+            }
+        }
+    }
+    counts
+}
+
+/// The product of the tree counts for each of `angles`.
+/// ```
+/// use aoc2020::toboggan as t;
+/// let m : t::Map =
+///     "..##.......\n\
+///      #...#...#..\n\
+///      .#....#..#.\n\
+///      ..#.#...#.#\n\
+///      .#...##..#.\n\
+///      ..#.##.....\n\
+///      .#.#.#....#\n\
+///      .#........#\n\
+///      #.##...#...\n\
+///      #...##....#\n\
+///      .#..#...#.#".parse().unwrap();
+/// let angles = [(1, 1), (3, 1), (5, 1), (7, 1), (1, 2)]
+///     .iter()
+///     .map(|&(right, down)| t::Angle{right, down})
+///     .collect::<Vec<_>>();
+/// assert_eq!(t::trees_for_angles(&m, &angles), vec![2, 7, 3, 4, 2]);
+/// assert_eq!(t::product_for_angles(&m, &angles), 336);
+/// ```
+pub fn product_for_angles(map: &Map, angles: &[Angle]) -> usize {
+    trees_for_angles(map, angles).iter().product()
+}
+
+/// The angle with the fewest trees among all combinations of `rights` and
+/// `downs`, with its tree count. Ties go to the first angle, ordered by
+/// `right` then `down`. Down moves of 0 are skipped.
+/// ```
+/// use aoc2020::toboggan as t;
+/// let m : t::Map = "...#\n\
+///                   .#.#\n\
+///                   ..##".parse().unwrap();
+/// assert_eq!(
+///     t::optimal_angle(&m, 0..=3, 1..=1),
+///     Some((t::Angle{right: 0, down: 1}, 0))
+/// );
+/// assert_eq!(
+///     t::optimal_angle(&m, 1..=3, 1..=2),
+///     Some((t::Angle{right: 1, down: 2}, 0))
+/// );
+/// assert_eq!(t::optimal_angle(&m, 1..=3, 0..=0), None);
+/// ```
+pub fn optimal_angle<R, D>(
+    map: &Map,
+    rights: R,
+    downs: D,
+) -> Option<(Angle, usize)>
+where
+    R: IntoIterator<Item = usize>,
+    D: IntoIterator<Item = usize> + Clone,
+{
+    let angles: Vec<Angle> = rights
+        .into_iter()
+        .flat_map(|right| {
+            downs
+                .clone()
+                .into_iter()
+                .filter(|down| *down > 0)
+                .map(move |down| Angle { right, down })
+        })
+        .collect();
+    let counts = trees_for_angles(map, &angles);
+    angles
+        .into_iter()
+        .zip(counts)
+        .min_by_key(|(_, count)| *count)
+}
+
+/// The (row, col) cells visited going down `angle`, starting at the top left
+/// corner. Columns are within the map, wrapping around its width.
+/// ```
+/// use aoc2020::toboggan as t;
+/// let m : t::Map = "...\n\
+///                   ...\n\
+///                   ...".parse().unwrap();
+/// assert_eq!(
+///     t::path(&m, t::Angle{right: 2, down: 1}),
+///     vec![(0, 0), (1, 2), (2, 1)]
+/// );
+/// assert_eq!(t::path(&m, t::Angle{right: 1, down: 2}), vec![(0, 0), (2, 1)]);
+/// ```
+pub fn path(map: &Map, angle: Angle) -> Vec<(usize, usize)> {
+    if angle.down == 0 {
+        return vec![(0, 0)];
+    }
+    (0..map.height())
+        .step_by(angle.down)
+        .map(|row| (row, row / angle.down * angle.right % map.width()))
+        .collect()
+}
+
+/// The cost of entering each kind of cell. Cells without a cost can't be
+/// entered.
+#[derive(Clone, Debug)]
+pub struct Costs {
+    costs: HashMap<Cell, u32>,
+}
+
+impl Default for Costs {
+    /// Open ground and ice are cheap, snow is slow, trees are best avoided
+    /// and rocks are impassable.
+    fn default() -> Costs {
+        Costs::impassable()
+            .with(Cell::Empty, Some(1))
+            .with(Cell::Ice, Some(1))
+            .with(Cell::Snow, Some(3))
+            .with(Cell::Tree, Some(10))
+    }
+}
+
+impl Costs {
+    /// No cell can be entered; add costs `with` the ones that can.
+    pub fn impassable() -> Costs {
+        Costs {
+            costs: HashMap::new(),
+        }
+    }
+
+    pub fn with(mut self, cell: Cell, cost: Option<u32>) -> Costs {
+        match cost {
+            Some(cost) => self.costs.insert(cell, cost),
+            None => self.costs.remove(&cell),
+        };
+        self
+    }
+
+    pub fn get(&self, cell: Cell) -> Option<u32> {
+        self.costs.get(&cell).copied()
+    }
+}
+
+/// Moves as (down, right) steps: down, and sideways in both directions.
+pub const DOWNHILL: [(isize, isize); 5] =
+    [(1, -1), (1, 0), (1, 1), (0, -1), (0, 1)];
+
+/// A cheapest route down the map.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Route {
+    /// The total cost of all cells on the route, including the first.
+    pub cost: u32,
+    /// The (row, col) cells visited, from the top row to the bottom one.
+    pub cells: Vec<(usize, usize)>,
+}
+
+/// Finds the cheapest route from any cell in the top row to any cell in the
+/// bottom row, taking `moves` as (down, right) steps. The map wraps
+/// horizontally, but not vertically.
+/// ```
+/// use aoc2020::toboggan as t;
+/// let m : t::Map = ".^^^\n\
+///                   #^^.\n\
+///                   ^^~.".parse().unwrap();
+/// let costs = t::Costs::default();
+/// let route = t::cheapest_route(&m, &costs, &t::DOWNHILL).unwrap();
+/// // Around the left edge rather than through the tree.
+/// assert_eq!(route.cells, vec![(0, 0), (1, 3), (2, 3)]);
+/// assert_eq!(route.cost, 3);
+///
+/// // Without diagonal moves, the tree is the only way down.
+/// let moves = [(1, 0), (0, 1), (0, -1)];
+/// let route = t::cheapest_route(&m, &costs, &moves).unwrap();
+/// assert_eq!(route.cells, vec![(0, 0), (1, 0), (1, 3), (2, 3)]);
+/// assert_eq!(route.cost, 13);
+///
+/// let costs = costs.with(t::Cell::Tree, None);
+/// assert_eq!(t::cheapest_route(&m, &costs, &moves), None);
+///
+/// let costs = t::Costs::impassable().with(t::Cell::Empty, Some(u32::MAX));
+/// let m: t::Map = ".\n.".parse().unwrap();
+/// assert_eq!(t::cheapest_route(&m, &costs, &moves), None);
+/// ```
+pub fn cheapest_route(
+    map: &Map,
+    costs: &Costs,
+    moves: &[(isize, isize)],
+) -> Option<Route> {
+    let (height, width) = (map.height(), map.width());
+    let cost_at = |idx: usize| costs.get(map.cells[idx]);
+
+    let mut best = vec![u32::MAX; map.cells.len()];
+    let mut prev = vec![None; map.cells.len()];
+    let mut queue = BinaryHeap::new();
+    for (idx, best) in best.iter_mut().enumerate().take(width) {
+        if let Some(cost) = cost_at(idx) {
+            *best = cost;
+            queue.push(Reverse((cost, idx)));
+        }
+    }
+
+    while let Some(Reverse((cost, idx))) = queue.pop() {
+        if cost > best[idx] {
+            continue;
+        }
+        let (row, col) = (idx / width, idx % width);
+        if row == height - 1 {
+            let mut cells = vec![(row, col)];
+            let mut idx = idx;
+            while let Some(p) = prev[idx] {
+                cells.push((p / width, p % width));
+                idx = p;
+            }
+            cells.reverse();
+            return Some(Route { cost, cells });
+        }
+        for (drow, dcol) in moves {
+            let next_row = row as isize + drow;
+            if !(0..height as isize).contains(&next_row) {
+                continue;
+            }
+            let next_col = (col as isize + dcol).rem_euclid(width as isize);
+            let next = next_row as usize * width + next_col as usize;
+            // Routes whose cost overflows are never the cheapest.
+            if let Some(next_cost) =
+                cost_at(next).and_then(|step| cost.checked_add(step))
+            {
+                if next_cost < best[next] {
+                    best[next] = next_cost;
+                    prev[next] = Some(idx);
+                    queue.push(Reverse((next_cost, next)));
+                }
+            }
+        }
+    }
+    None
+}